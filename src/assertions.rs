@@ -0,0 +1,351 @@
+use num_bigint::BigUint;
+use num_traits::{Num, ToPrimitive};
+use serde::Serialize;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{Assertion, Config, Expectation};
+use crate::{encode_hex, eth_call, is_valid_eth_address, strip_hex_prefix};
+
+#[derive(Serialize)]
+pub struct AssertionResponse {
+    target: String,
+    assertion: String,
+    contract: String,
+    signature: String,
+    result: String,
+    value: String,
+    expected: String,
+    status: String,
+}
+
+fn describe(expect: &Expectation) -> String {
+    match expect {
+        Expectation::Equals { value } => format!("equals {}", value),
+        Expectation::GreaterThan { value } => format!("greater_than {}", value),
+        Expectation::LessThan { value } => format!("less_than {}", value),
+        Expectation::MaxAge { seconds } => format!("max_age {}s", seconds),
+    }
+}
+
+/// The first 32-byte return word as an unsigned integer; `bool`, `address` and
+/// `uintN` returns are all left-padded into it.
+fn first_word(data: &[u8]) -> Result<BigUint, String> {
+    if data.len() < 32 {
+        return Err(format!(
+            "call returned {} bytes, expected at least 32 (reverted or not a contract?)",
+            data.len()
+        ));
+    }
+    Ok(BigUint::from_bytes_be(&data[..32]))
+}
+
+/// Parse an expected value: `true`/`false`, `0x` hex, or a decimal integer.
+/// Addresses are plain hex too, so they compare against the padded word.
+fn parse_expected(value: &str) -> Result<BigUint, String> {
+    match value {
+        "true" => Ok(BigUint::from(1u32)),
+        "false" => Ok(BigUint::from(0u32)),
+        v if v.starts_with("0x") || v.starts_with("0X") => {
+            BigUint::from_str_radix(strip_hex_prefix(v), 16)
+                .map_err(|_| format!("invalid expected hex value '{}'", v))
+        }
+        v => BigUint::from_str(v).map_err(|_| format!("invalid expected value '{}'", v)),
+    }
+}
+
+/// Format the decoded word the way the expectation is written, so the response
+/// shows comparable values (an address for address expectations).
+fn display_value(expect: &Expectation, word: &BigUint) -> String {
+    match expect {
+        Expectation::Equals { value } if is_valid_eth_address(value) => {
+            format!("0x{:0>40}", word.to_str_radix(16))
+        }
+        _ => word.to_string(),
+    }
+}
+
+/// Check the call's return data against the expectation. `now` is unix seconds.
+fn evaluate(expect: &Expectation, data: &[u8], now: u64) -> Result<bool, String> {
+    let word = first_word(data)?;
+    match expect {
+        Expectation::Equals { value } => Ok(word == parse_expected(value)?),
+        Expectation::GreaterThan { value } => Ok(word > parse_expected(value)?),
+        Expectation::LessThan { value } => Ok(word < parse_expected(value)?),
+        Expectation::MaxAge { seconds } => {
+            let timestamp = word
+                .to_u64()
+                .ok_or("timestamp does not fit in 64 bits".to_string())?;
+            // A timestamp slightly in the future (clock skew) counts as fresh.
+            Ok(now.saturating_sub(timestamp) <= *seconds)
+        }
+    }
+}
+
+/// Run one configured assertion against `rpc`, returning the HTTP status it maps to:
+/// 200 when it holds, 503 when it is violated and 500 when the call itself fails.
+pub async fn run_assertion(
+    target: &str,
+    rpc: &str,
+    assertion: &Assertion,
+) -> (warp::http::StatusCode, AssertionResponse) {
+    let mut response = AssertionResponse {
+        target: target.to_string(),
+        assertion: assertion.name.clone(),
+        contract: assertion.contract.clone(),
+        signature: assertion.signature.clone(),
+        result: "0x".to_string(),
+        value: "".to_string(),
+        expected: describe(&assertion.expect),
+        status: "".to_string(),
+    };
+
+    let data = match eth_call(
+        Some(rpc.to_string()),
+        &assertion.contract,
+        &assertion.signature,
+        &assertion.args,
+    )
+    .await
+    {
+        Ok(d) => d,
+        Err(e) => {
+            response.status = format!("error: {}", e);
+            return (warp::http::StatusCode::INTERNAL_SERVER_ERROR, response);
+        }
+    };
+
+    response.result = format!("0x{}", encode_hex(&data));
+    if let Ok(word) = first_word(&data) {
+        response.value = display_value(&assertion.expect, &word);
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    match evaluate(&assertion.expect, &data, now) {
+        Ok(true) => {
+            response.status = "assertion_passed".to_string();
+            (warp::http::StatusCode::OK, response)
+        }
+        Ok(false) => {
+            response.status = "assertion_failed".to_string();
+            (warp::http::StatusCode::SERVICE_UNAVAILABLE, response)
+        }
+        Err(e) => {
+            response.status = format!("error: {}", e);
+            (warp::http::StatusCode::INTERNAL_SERVER_ERROR, response)
+        }
+    }
+}
+
+/// HTTP handler for `/assertion?target=<name>&name=<assertion>`: runs a configured
+/// contract-call assertion. Unknown targets or assertions return 404.
+pub async fn check_assertion(
+    config: Arc<Config>,
+    target: String,
+    name: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let found = config
+        .target(&target)
+        .and_then(|t| t.assertion(&name).map(|a| (t, a)));
+
+    let (target_config, assertion) = match found {
+        Some(pair) => pair,
+        None => {
+            let error_response = AssertionResponse {
+                target: target.clone(),
+                assertion: name.clone(),
+                contract: "".to_string(),
+                signature: "".to_string(),
+                result: "0x".to_string(),
+                value: "".to_string(),
+                expected: "".to_string(),
+                status: format!("error: no assertion '{}' on target '{}'", name, target),
+            };
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&error_response),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    };
+
+    let (status, response) =
+        run_assertion(&target_config.name, &target_config.rpc, assertion).await;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&response),
+        status,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+    use crate::tests::echo_rpc_id;
+    use mockito::Server;
+    use warp::Reply;
+
+    fn word(value: u64) -> Vec<u8> {
+        let mut data = vec![0u8; 24];
+        data.extend_from_slice(&value.to_be_bytes());
+        data
+    }
+
+    fn equals(value: &str) -> Expectation {
+        Expectation::Equals {
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_equals_bool() {
+        assert!(evaluate(&equals("false"), &word(0), 0).unwrap());
+        assert!(!evaluate(&equals("false"), &word(1), 0).unwrap());
+        assert!(evaluate(&equals("true"), &word(1), 0).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_equals_address() {
+        let mut data = vec![0u8; 12];
+        data.extend_from_slice(&[0xab; 20]);
+        let owner = format!("0x{}", "ab".repeat(20));
+        assert!(evaluate(&equals(&owner), &data, 0).unwrap());
+        assert!(!evaluate(
+            &equals("0x0000000000000000000000000000000000000001"),
+            &data,
+            0
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_comparisons() {
+        let gt = Expectation::GreaterThan {
+            value: "10".to_string(),
+        };
+        let lt = Expectation::LessThan {
+            value: "0x10".to_string(),
+        };
+        assert!(evaluate(&gt, &word(11), 0).unwrap());
+        assert!(!evaluate(&gt, &word(10), 0).unwrap());
+        assert!(evaluate(&lt, &word(15), 0).unwrap());
+        assert!(!evaluate(&lt, &word(16), 0).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_max_age() {
+        let expect = Expectation::MaxAge { seconds: 60 };
+        assert!(evaluate(&expect, &word(1_000), 1_060).unwrap());
+        assert!(!evaluate(&expect, &word(1_000), 1_061).unwrap());
+        // Slightly future timestamps count as fresh.
+        assert!(evaluate(&expect, &word(2_000), 1_000).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_short_return_data_is_error() {
+        assert!(evaluate(&equals("0"), &[], 0).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_invalid_expected_value_is_error() {
+        assert!(evaluate(&equals("not-a-number"), &word(0), 0).is_err());
+    }
+
+    fn config_for(rpc: &str) -> Arc<Config> {
+        Arc::new(
+            parse_config(&format!(
+                r#"{{"targets":[{{"name":"l1","rpc":"{}","assertions":[{{
+                    "name":"not_paused",
+                    "contract":"0x0000000000000000000000000000000000000001",
+                    "signature":"paused()",
+                    "expect":{{"op":"equals","value":"false"}}}}]}}]}}"#,
+                rpc
+            ))
+            .unwrap(),
+        )
+    }
+
+    async fn assertion_status(
+        config: Arc<Config>,
+        target: &str,
+        name: &str,
+    ) -> warp::http::StatusCode {
+        check_assertion(config, target.to_string(), name.to_string())
+            .await
+            .unwrap()
+            .into_response()
+            .status()
+    }
+
+    async fn mock_call_result(server: &mut mockito::ServerGuard, result: &str) {
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":"{}"}}"#,
+                result
+            )))
+            .create_async()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_check_assertion_passes() {
+        let mut server = Server::new_async().await;
+        mock_call_result(&mut server, &format!("0x{}", "00".repeat(32))).await;
+
+        assert_eq!(
+            assertion_status(config_for(&server.url()), "l1", "not_paused").await,
+            warp::http::StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_assertion_violated_returns_unavailable() {
+        let mut server = Server::new_async().await;
+        mock_call_result(&mut server, &format!("0x{}01", "00".repeat(31))).await;
+
+        assert_eq!(
+            assertion_status(config_for(&server.url()), "l1", "not_paused").await,
+            warp::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_assertion_revert_returns_error() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(
+                r#"{"jsonrpc":"2.0","id":1,"error":{"code":3,"message":"execution reverted"}}"#,
+            ))
+            .create_async()
+            .await;
+
+        assert_eq!(
+            assertion_status(config_for(&server.url()), "l1", "not_paused").await,
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_assertion_unknown_returns_not_found() {
+        let config = config_for("http://127.0.0.1:1");
+        assert_eq!(
+            assertion_status(config.clone(), "l1", "missing").await,
+            warp::http::StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            assertion_status(config, "missing", "not_paused").await,
+            warp::http::StatusCode::NOT_FOUND
+        );
+    }
+}
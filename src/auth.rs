@@ -0,0 +1,249 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use warp::http::{HeaderValue, Request, Response, StatusCode};
+use warp::hyper::Body;
+
+/// Probe endpoints stay open so the kubelet can reach them without a key.
+pub const OPEN_PATHS: [&str; 2] = ["/livez", "/readyz"];
+
+/// Query parameters that point the monitor at a caller-chosen node. Only admin
+/// keys may use them: they make the monitor connect to arbitrary hosts and sockets.
+const ADMIN_PARAMS: [&str; 3] = ["rpc", "l1_rpc", "beacon"];
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Scope {
+    Read,
+    Admin,
+}
+
+impl Scope {
+    fn parse(name: &str) -> Result<Scope, String> {
+        match name {
+            "read" => Ok(Scope::Read),
+            "admin" => Ok(Scope::Admin),
+            other => Err(format!(
+                "unknown API key scope '{}' (expected read or admin)",
+                other
+            )),
+        }
+    }
+}
+
+/// Accepted API keys and their scopes.
+pub struct ApiKeys {
+    keys: HashMap<String, Scope>,
+}
+
+impl ApiKeys {
+    /// Keys from `API_KEYS` and the file named by `API_KEYS_FILE`. `None` when
+    /// neither is set, which leaves the API unauthenticated.
+    pub fn from_env() -> Result<Option<ApiKeys>, Box<dyn std::error::Error>> {
+        let mut entries = Vec::new();
+        if let Ok(list) = env::var("API_KEYS") {
+            entries.extend(list.split(',').map(str::to_string));
+        }
+        if let Ok(path) = env::var("API_KEYS_FILE") {
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| format!("failed to read API keys file '{}': {}", path, e))?;
+            entries.extend(
+                contents
+                    .lines()
+                    .filter(|l| !l.trim_start().starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+        if entries.is_empty() {
+            return Ok(None);
+        }
+        let keys = Self::parse(entries.iter().map(String::as_str))?;
+        if keys.keys.is_empty() {
+            return Err("API_KEYS/API_KEYS_FILE set but no keys found".into());
+        }
+        Ok(Some(keys))
+    }
+
+    /// Parse `scope:key` entries; a bare `key` is read-only. Blank entries are skipped.
+    fn parse<'a>(entries: impl Iterator<Item = &'a str>) -> Result<ApiKeys, String> {
+        let mut keys = HashMap::new();
+        for entry in entries.map(str::trim).filter(|e| !e.is_empty()) {
+            let (scope, key) = match entry.split_once(':') {
+                Some((scope, key)) => (Scope::parse(scope.trim())?, key.trim()),
+                None => (Scope::Read, entry),
+            };
+            if key.is_empty() {
+                return Err("empty API key".to_string());
+            }
+            keys.insert(key.to_string(), scope);
+        }
+        Ok(ApiKeys { keys })
+    }
+
+    /// The scope of `presented`, comparing against every key in constant time.
+    fn scope_of(&self, presented: &str) -> Option<Scope> {
+        let mut found = None;
+        for (key, scope) in &self.keys {
+            if constant_time_eq(key.as_bytes(), presented.as_bytes()) {
+                found = Some(*scope);
+            }
+        }
+        found
+    }
+
+    /// The 401 or 403 response to reject `req` with, or `None` when it may proceed.
+    pub fn deny(&self, req: &Request<Body>) -> Option<Response<Body>> {
+        let required = required_scope(req)?;
+        match presented_key(req).and_then(|key| self.scope_of(key)) {
+            None => Some(error_response(
+                StatusCode::UNAUTHORIZED,
+                "error: missing or invalid API key",
+            )),
+            Some(scope) if scope < required => Some(error_response(
+                StatusCode::FORBIDDEN,
+                "error: API key is read-only; rpc, l1_rpc and beacon overrides need an admin key",
+            )),
+            Some(_) => None,
+        }
+    }
+}
+
+/// The scope `req` needs, or `None` for an open path.
+fn required_scope(req: &Request<Body>) -> Option<Scope> {
+    if OPEN_PATHS.contains(&req.uri().path()) {
+        return None;
+    }
+    let overrides_node = req.uri().query().is_some_and(|query| {
+        reqwest::Url::parse(&format!("http://localhost/?{}", query))
+            .map(|url| url.query_pairs().any(|(k, _)| ADMIN_PARAMS.contains(&&*k)))
+            .unwrap_or(true)
+    });
+    Some(if overrides_node {
+        Scope::Admin
+    } else {
+        Scope::Read
+    })
+}
+
+/// The key from `Authorization: Bearer <key>` or `X-API-Key: <key>`.
+fn presented_key(req: &Request<Body>) -> Option<&str> {
+    let headers = req.headers();
+    if let Some(value) = headers.get("authorization").and_then(|v| v.to_str().ok()) {
+        if let Some(token) = value.strip_prefix("Bearer ") {
+            return Some(token.trim());
+        }
+    }
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Serialize)]
+struct AuthErrorResponse {
+    status: String,
+}
+
+pub fn error_response(code: StatusCode, status: &str) -> Response<Body> {
+    let body = serde_json::to_string(&AuthErrorResponse {
+        status: status.to_string(),
+    })
+    .unwrap_or_default();
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = code;
+    let headers = response.headers_mut();
+    headers.insert("content-type", HeaderValue::from_static("application/json"));
+    if code == StatusCode::UNAUTHORIZED {
+        headers.insert("www-authenticate", HeaderValue::from_static("Bearer"));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> ApiKeys {
+        ApiKeys::parse(["dashboards", "read:grafana", " admin: ops-key "].into_iter()).unwrap()
+    }
+
+    fn request(uri: &str, header: Option<(&str, &str)>) -> Request<Body> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some((name, value)) = header {
+            builder = builder.header(name, value);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn status(denied: Option<Response<Body>>) -> StatusCode {
+        denied.map_or(StatusCode::OK, |response| response.status())
+    }
+
+    #[test]
+    fn test_parse_scopes() {
+        let keys = keys();
+        assert_eq!(keys.scope_of("dashboards"), Some(Scope::Read));
+        assert_eq!(keys.scope_of("grafana"), Some(Scope::Read));
+        assert_eq!(keys.scope_of("ops-key"), Some(Scope::Admin));
+        assert_eq!(keys.scope_of("ops"), None);
+
+        assert!(ApiKeys::parse(["root:key"].into_iter()).is_err());
+        assert!(ApiKeys::parse(["admin:"].into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_missing_or_invalid_key_is_unauthorized() {
+        let keys = keys();
+        let response = keys.deny(&request("/gas", None)).unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()["www-authenticate"], "Bearer");
+
+        let wrong = request("/gas", Some(("authorization", "Bearer nope")));
+        assert_eq!(status(keys.deny(&wrong)), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_probes_stay_open() {
+        let keys = keys();
+        assert_eq!(status(keys.deny(&request("/livez", None))), StatusCode::OK);
+        assert_eq!(status(keys.deny(&request("/readyz", None))), StatusCode::OK);
+        assert_eq!(
+            status(keys.deny(&request("/health", None))),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn test_bearer_and_api_key_headers() {
+        let keys = keys();
+        let bearer = request("/health", Some(("authorization", "Bearer grafana")));
+        assert_eq!(status(keys.deny(&bearer)), StatusCode::OK);
+        let header = request("/health", Some(("x-api-key", "dashboards")));
+        assert_eq!(status(keys.deny(&header)), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_node_overrides_need_admin_scope() {
+        let keys = keys();
+        for uri in [
+            "/gas?rpc=http://10.0.0.1:8545",
+            "/proposal_lag?l1_rpc=x",
+            "/beacon_status?beacon=x",
+            // Encoded parameter names are decoded before the check.
+            "/gas?%72pc=http://10.0.0.1:8545",
+        ] {
+            let read = request(uri, Some(("x-api-key", "grafana")));
+            assert_eq!(status(keys.deny(&read)), StatusCode::FORBIDDEN, "{}", uri);
+            let admin = request(uri, Some(("x-api-key", "ops-key")));
+            assert_eq!(status(keys.deny(&admin)), StatusCode::OK, "{}", uri);
+        }
+        let read = request("/gas?blocks=10", Some(("x-api-key", "grafana")));
+        assert_eq!(status(keys.deny(&read)), StatusCode::OK);
+    }
+}
@@ -0,0 +1,410 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{get_block_number, is_valid_eth_address, rpc_batch, strip_hex_prefix, BatchRejections};
+
+/// ~1 hour of L1 blocks at 12s slots.
+const DEFAULT_LOOKBACK_BLOCKS: u64 = 300;
+/// ~3 hours; every scanned block is fetched with its full transaction list.
+const MAX_LOOKBACK_BLOCKS: u64 = 900;
+/// Full blocks fetched per batch request; L1 blocks can run to hundreds of KB each.
+const BLOCKS_PER_BATCH: u64 = 20;
+
+#[derive(Serialize)]
+struct BatchLagResponse {
+    batcher: String,
+    inbox: String,
+    l1_head: i64,
+    blocks_scanned: u64,
+    last_submission_block: Option<i64>,
+    last_submission_timestamp: Option<u64>,
+    last_submission_tx: Option<String>,
+    seconds_since_submission: Option<u64>,
+    max_seconds: Option<u64>,
+    status: String,
+}
+
+/// A batch transaction found on L1.
+struct Submission {
+    block: i64,
+    timestamp: u64,
+    tx_hash: String,
+}
+
+fn same_address(a: Option<&str>, b: &str) -> bool {
+    a.map(|a| a.eq_ignore_ascii_case(b)).unwrap_or(false)
+}
+
+/// The first transaction sent from `batcher` to `inbox` in `block` (fetched with full
+/// transactions), if any.
+fn find_submission_in_block(
+    number: i64,
+    block: &Value,
+    batcher: &str,
+    inbox: &str,
+) -> Result<Option<Submission>, Box<dyn std::error::Error>> {
+    let timestamp = block
+        .get("timestamp")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| format!("block {} has no timestamp", number))?;
+    let timestamp = u64::from_str_radix(strip_hex_prefix(timestamp), 16)?;
+
+    let transactions = block
+        .get("transactions")
+        .and_then(|t| t.as_array())
+        .ok_or_else(|| format!("block {} has no transaction list", number))?;
+
+    let submission = transactions.iter().find(|tx| {
+        same_address(tx.get("from").and_then(|f| f.as_str()), batcher)
+            && same_address(tx.get("to").and_then(|t| t.as_str()), inbox)
+    });
+
+    Ok(submission.map(|tx| Submission {
+        block: number,
+        timestamp,
+        tx_hash: tx
+            .get("hash")
+            .and_then(|h| h.as_str())
+            .unwrap_or_default()
+            .to_string(),
+    }))
+}
+
+/// Walk back `lookback` blocks from `head`, `BLOCKS_PER_BATCH` full blocks per batch
+/// request, and return the newest submission with the number of blocks scanned to
+/// reach it.
+async fn find_latest_submission(
+    rpc_url: Option<String>,
+    head: i64,
+    lookback: u64,
+    batcher: &str,
+    inbox: &str,
+    rejections: &BatchRejections,
+) -> Result<(Option<Submission>, u64), Box<dyn std::error::Error>> {
+    let oldest = (head - lookback as i64 + 1).max(0);
+    let mut newest = head;
+    while newest >= oldest {
+        let numbers: Vec<i64> = (oldest.max(newest - BLOCKS_PER_BATCH as i64 + 1)..=newest)
+            .rev()
+            .collect();
+        let calls = numbers
+            .iter()
+            .map(|number| {
+                (
+                    "eth_getBlockByNumber",
+                    vec![Value::String(format!("0x{:x}", number)), Value::Bool(true)],
+                )
+            })
+            .collect();
+        let blocks = rpc_batch(rpc_url.clone(), calls, rejections).await?;
+        for (number, block) in numbers.iter().zip(&blocks) {
+            if let Some(submission) = find_submission_in_block(*number, block, batcher, inbox)? {
+                return Ok((Some(submission), (head - number + 1) as u64));
+            }
+        }
+        newest -= numbers.len() as i64;
+    }
+    Ok((None, (head - oldest + 1) as u64))
+}
+
+/// HTTP handler for `/batch_lag`. Walks back from the L1 head looking for the newest
+/// transaction from `batcher` to `inbox`; returns 503 when none landed within
+/// `max_seconds` (or within the scanned `lookback` blocks) and 500 on RPC failure.
+pub async fn check_batch_lag(
+    rpc_url: Option<String>,
+    batcher: String,
+    inbox: String,
+    max_seconds: Option<u64>,
+    lookback: Option<u64>,
+    batch_rejections: Arc<BatchRejections>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut response = BatchLagResponse {
+        batcher: batcher.clone(),
+        inbox: inbox.clone(),
+        l1_head: 0,
+        blocks_scanned: 0,
+        last_submission_block: None,
+        last_submission_timestamp: None,
+        last_submission_tx: None,
+        seconds_since_submission: None,
+        max_seconds,
+        status: "".to_string(),
+    };
+
+    if !is_valid_eth_address(&batcher) || !is_valid_eth_address(&inbox) {
+        response.status =
+            "error: invalid or missing batcher/inbox address (expected 0x + 40 hex digits)"
+                .to_string();
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let lookback = lookback
+        .filter(|l| *l > 0)
+        .unwrap_or(DEFAULT_LOOKBACK_BLOCKS)
+        .min(MAX_LOOKBACK_BLOCKS);
+
+    let head = match get_block_number(rpc_url.clone()).await {
+        Ok(n) => n,
+        Err(e) => {
+            response.status = format!("error: {}", e);
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&response),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+    response.l1_head = head;
+
+    let submission =
+        match find_latest_submission(rpc_url, head, lookback, &batcher, &inbox, &batch_rejections)
+            .await
+        {
+            Ok((submission, scanned)) => {
+                response.blocks_scanned = scanned;
+                submission
+            }
+            Err(e) => {
+                response.status = format!("error: {}", e);
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&response),
+                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+                ));
+            }
+        };
+
+    let submission = match submission {
+        Some(s) => s,
+        None => {
+            response.status = "no_batch_in_lookback".to_string();
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&response),
+                warp::http::StatusCode::SERVICE_UNAVAILABLE,
+            ));
+        }
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let since = now.saturating_sub(submission.timestamp);
+
+    response.last_submission_block = Some(submission.block);
+    response.last_submission_timestamp = Some(submission.timestamp);
+    response.last_submission_tx = Some(submission.tx_hash);
+    response.seconds_since_submission = Some(since);
+
+    if max_seconds.map(|m| since > m).unwrap_or(false) {
+        response.status = "batch_lagging".to_string();
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    } else {
+        response.status = "batches_current".to_string();
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::rpc_node;
+    use mockito::Server;
+    use std::collections::HashMap;
+    use warp::Reply;
+
+    const BATCHER: &str = "0x6887246668a3b87F54DeB3b94Ba47a6f63F32985";
+    const INBOX: &str = "0xff00000000000000000000000000000000000010";
+    const OTHER: &str = "0x0000000000000000000000000000000000000001";
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    /// `(from, to)` of each transaction in a block.
+    type Txs<'a> = &'a [(&'a str, &'a str)];
+
+    /// A node at `head` serving `eth_getBlockByNumber` singly or batched. `blocks`
+    /// lists `(number, timestamp, [(from, to)])`; other blocks have no transactions.
+    async fn mock_chain(
+        server: &mut mockito::ServerGuard,
+        head: u64,
+        blocks: &[(u64, u64, Txs<'_>)],
+    ) -> mockito::Mock {
+        let blocks: HashMap<String, String> = blocks
+            .iter()
+            .map(|(number, timestamp, txs)| {
+                let txs: Vec<String> = txs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (from, to))| {
+                        format!(
+                            r#"{{"hash":"0x{:064x}","from":"{}","to":"{}"}}"#,
+                            i + 1,
+                            from,
+                            to
+                        )
+                    })
+                    .collect();
+                (
+                    format!("0x{:x}", number),
+                    format!(
+                        r#"{{"jsonrpc":"2.0","id":1,"result":{{"number":"0x{:x}","timestamp":"0x{:x}","transactions":[{}]}}}}"#,
+                        number,
+                        timestamp,
+                        txs.join(",")
+                    ),
+                )
+            })
+            .collect();
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(rpc_node(move |req| {
+                if req["method"] == "eth_blockNumber" {
+                    return format!(r#"{{"jsonrpc":"2.0","id":1,"result":"0x{:x}"}}"#, head);
+                }
+                let number = req["params"][0].as_str().unwrap();
+                blocks.get(number).cloned().unwrap_or_else(|| {
+                    format!(
+                        r#"{{"jsonrpc":"2.0","id":1,"result":{{"number":"{}","timestamp":"0x1","transactions":[]}}}}"#,
+                        number
+                    )
+                })
+            }))
+            .create_async()
+            .await
+    }
+
+    async fn batch_json(
+        server: &mockito::ServerGuard,
+        max_seconds: Option<u64>,
+        lookback: Option<u64>,
+    ) -> (warp::http::StatusCode, serde_json::Value) {
+        let response = check_batch_lag(
+            Some(server.url()),
+            BATCHER.to_string(),
+            INBOX.to_string(),
+            max_seconds,
+            lookback,
+            Default::default(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let status = response.status();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_recent_batch_returns_ok() {
+        let mut server = Server::new_async().await;
+        // Address comparison is case-insensitive.
+        let batcher = BATCHER.to_lowercase();
+        mock_chain(
+            &mut server,
+            100,
+            &[
+                (100, now(), &[(OTHER, INBOX)]),
+                (99, now() - 24, &[(OTHER, OTHER), (&batcher, INBOX)]),
+            ],
+        )
+        .await;
+
+        let (status, json) = batch_json(&server, Some(600), None).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(json["last_submission_block"], 99);
+        assert_eq!(json["blocks_scanned"], 2);
+        assert_eq!(json["last_submission_tx"], format!("0x{:064x}", 2));
+    }
+
+    #[tokio::test]
+    async fn test_stale_batch_returns_unavailable() {
+        let mut server = Server::new_async().await;
+        mock_chain(
+            &mut server,
+            100,
+            &[(100, now() - 1_200, &[(BATCHER, INBOX)])],
+        )
+        .await;
+
+        let (status, json) = batch_json(&server, Some(600), None).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["status"], "batch_lagging");
+    }
+
+    #[tokio::test]
+    async fn test_no_batch_in_lookback_returns_unavailable() {
+        let mut server = Server::new_async().await;
+        mock_chain(
+            &mut server,
+            100,
+            &[(100, now(), &[]), (99, now(), &[(BATCHER, OTHER)])],
+        )
+        .await;
+
+        let (status, json) = batch_json(&server, Some(600), Some(2)).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["status"], "no_batch_in_lookback");
+        assert_eq!(json["blocks_scanned"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_lookback_is_fetched_in_batches() {
+        let mut server = Server::new_async().await;
+        let mock = mock_chain(&mut server, 100, &[(70, now(), &[(BATCHER, INBOX)])]).await;
+
+        let (status, json) = batch_json(&server, None, None).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(json["last_submission_block"], 70);
+        assert_eq!(json["blocks_scanned"], 31);
+        // eth_blockNumber, then blocks 100..=81 and 80..=61 in two batches.
+        mock.expect(3).assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_invalid_address_returns_bad_request() {
+        let status = check_batch_lag(
+            None,
+            "0x01".to_string(),
+            INBOX.to_string(),
+            None,
+            None,
+            Default::default(),
+        )
+        .await
+        .unwrap()
+        .into_response()
+        .status();
+        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_failure_returns_error() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(500)
+            .create_async()
+            .await;
+
+        let (status, _) = batch_json(&server, None, None).await;
+        assert_eq!(status, warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,478 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use crate::telemetry;
+
+const DEFAULT_BEACON_URL: &str = "http://127.0.0.1:5052";
+const DEFAULT_SLOTS_PER_EPOCH: u64 = 32;
+
+/// Beacon API envelope: every JSON endpoint wraps its payload in `data`.
+#[derive(Deserialize)]
+struct BeaconData<T> {
+    data: T,
+}
+
+/// Beacon API integers are encoded as decimal strings ("uint64" in the spec).
+fn parse_u64_string<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse::<u64>().map_err(serde::de::Error::custom)
+}
+
+#[derive(Deserialize)]
+struct SyncingData {
+    #[serde(deserialize_with = "parse_u64_string")]
+    head_slot: u64,
+    #[serde(deserialize_with = "parse_u64_string")]
+    sync_distance: u64,
+    is_syncing: bool,
+    // Older clients predate these fields.
+    #[serde(default)]
+    is_optimistic: bool,
+    #[serde(default)]
+    el_offline: bool,
+}
+
+#[derive(Deserialize)]
+struct Checkpoint {
+    #[serde(deserialize_with = "parse_u64_string")]
+    epoch: u64,
+}
+
+#[derive(Deserialize)]
+struct FinalityCheckpoints {
+    finalized: Checkpoint,
+}
+
+#[derive(Serialize)]
+struct BeaconStatusResponse {
+    head_slot: u64,
+    head_epoch: u64,
+    sync_distance: u64,
+    is_syncing: bool,
+    is_optimistic: bool,
+    el_offline: bool,
+    health: String,
+    finalized_epoch: u64,
+    epochs_since_finalization: u64,
+    status: String,
+}
+
+impl BeaconStatusResponse {
+    fn error(status: String) -> Self {
+        BeaconStatusResponse {
+            head_slot: 0,
+            head_epoch: 0,
+            sync_distance: 0,
+            is_syncing: false,
+            is_optimistic: false,
+            el_offline: false,
+            health: "".to_string(),
+            finalized_epoch: 0,
+            epochs_since_finalization: 0,
+            status,
+        }
+    }
+}
+
+async fn beacon_get(
+    beacon_url: &str,
+    path: &str,
+) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+    let url = format!("{}{}", beacon_url.trim_end_matches('/'), path);
+    let mut request = Client::new().get(&url).timeout(crate::RPC_TIMEOUT);
+    if let Some(id) = telemetry::request_id() {
+        request = request.header(telemetry::REQUEST_ID_HEADER, id);
+    }
+
+    let started = Instant::now();
+    // reqwest errors carry the URL, and beacon URLs can hold provider API keys.
+    let outcome = request.send().await.map_err(reqwest::Error::without_url);
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match &outcome {
+        Ok(r) => tracing::info!(
+            beacon.path = path,
+            latency_ms,
+            http.status = r.status().as_u16(),
+            "beacon call"
+        ),
+        Err(e) => {
+            tracing::warn!(beacon.path = path, latency_ms, outcome = "transport_error", error = %e, "beacon call")
+        }
+    }
+    Ok(outcome?)
+}
+
+/// Fetch `/eth/v1/node/syncing` from the consensus client.
+async fn get_beacon_syncing(beacon_url: &str) -> Result<SyncingData, Box<dyn std::error::Error>> {
+    let response = beacon_get(beacon_url, "/eth/v1/node/syncing")
+        .await?
+        .error_for_status()
+        .map_err(reqwest::Error::without_url)?;
+    let body = response.text().await.map_err(reqwest::Error::without_url)?;
+    let syncing: BeaconData<SyncingData> = serde_json::from_str(&body)?;
+
+    tracing::debug!(
+        head_slot = %syncing.data.head_slot,
+        sync_distance = %syncing.data.sync_distance,
+        is_syncing = syncing.data.is_syncing,
+        "beacon syncing"
+    );
+
+    Ok(syncing.data)
+}
+
+/// Query `/eth/v1/node/health`. The endpoint has no body; the node's state is the
+/// status code: 200 ready, 206 syncing, 503 not initialized or having issues.
+async fn get_beacon_health(beacon_url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let response = beacon_get(beacon_url, "/eth/v1/node/health").await?;
+
+    let health = match response.status().as_u16() {
+        200 => "ready",
+        206 => "syncing",
+        503 => "not_ready",
+        other => return Err(format!("unexpected beacon health status {}", other).into()),
+    };
+
+    Ok(health.to_string())
+}
+
+/// Fetch the finalized epoch from `/eth/v1/beacon/states/head/finality_checkpoints`.
+async fn get_finalized_epoch(beacon_url: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    let response = beacon_get(
+        beacon_url,
+        "/eth/v1/beacon/states/head/finality_checkpoints",
+    )
+    .await?
+    .error_for_status()
+    .map_err(reqwest::Error::without_url)?;
+    let body = response.text().await.map_err(reqwest::Error::without_url)?;
+    let checkpoints: BeaconData<FinalityCheckpoints> = serde_json::from_str(&body)?;
+
+    Ok(checkpoints.data.finalized.epoch)
+}
+
+/// HTTP handler for `/beacon_status`. Returns 200 when the consensus client is ready,
+/// has a live execution client, is not following the head optimistically, is within
+/// `max_sync_distance` slots of the network head and has finalized within
+/// `max_finality_epochs` epochs; 503 otherwise, mirroring `finalized_latest_diff`.
+pub async fn check_beacon(
+    beacon_url: Option<String>,
+    max_sync_distance: Option<u64>,
+    max_finality_epochs: Option<u64>,
+    slots_per_epoch: Option<u64>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let beacon = beacon_url.unwrap_or_else(|| DEFAULT_BEACON_URL.to_string());
+    let slots_per_epoch = slots_per_epoch
+        .filter(|s| *s > 0)
+        .unwrap_or(DEFAULT_SLOTS_PER_EPOCH);
+
+    let syncing = match get_beacon_syncing(&beacon).await {
+        Ok(s) => s,
+        Err(e) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&BeaconStatusResponse::error(format!("error: {}", e))),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+
+    let health = match get_beacon_health(&beacon).await {
+        Ok(h) => h,
+        Err(e) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&BeaconStatusResponse::error(format!("error: {}", e))),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+
+    let finalized_epoch = match get_finalized_epoch(&beacon).await {
+        Ok(e) => e,
+        Err(e) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&BeaconStatusResponse::error(format!("error: {}", e))),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+
+    let head_epoch = syncing.head_slot / slots_per_epoch;
+    let epochs_since_finalization = head_epoch.saturating_sub(finalized_epoch);
+
+    // An optimistic head has not been validated by the execution client, and with the
+    // EL offline the node cannot validate anything, however close to the head it is.
+    let status = if syncing.el_offline {
+        "el_offline"
+    } else if health != "ready" || syncing.is_syncing {
+        "not_synced"
+    } else if syncing.is_optimistic {
+        "optimistic"
+    } else if syncing.sync_distance > max_sync_distance.unwrap_or(1) {
+        "sync_distance_exceeded"
+    } else if epochs_since_finalization > max_finality_epochs.unwrap_or(3) {
+        "finality_lagging"
+    } else {
+        "synced"
+    };
+
+    let response = BeaconStatusResponse {
+        head_slot: syncing.head_slot,
+        head_epoch,
+        sync_distance: syncing.sync_distance,
+        is_syncing: syncing.is_syncing,
+        is_optimistic: syncing.is_optimistic,
+        el_offline: syncing.el_offline,
+        health,
+        finalized_epoch,
+        epochs_since_finalization,
+        status: status.to_string(),
+    };
+
+    if status == "synced" {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::OK,
+        ))
+    } else {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+    use warp::Reply;
+
+    #[tokio::test]
+    async fn test_transport_error_does_not_leak_the_beacon_url() {
+        let (_guard, logs) = crate::tests::capture_logs();
+        let err = get_beacon_syncing("http://127.0.0.1:1/v2/SECRET")
+            .await
+            .err()
+            .unwrap();
+        assert!(!err.to_string().contains("SECRET"), "{}", err);
+        let logs = String::from_utf8(logs.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("transport_error"), "{}", logs);
+        assert!(!logs.contains("SECRET"), "{}", logs);
+    }
+
+    const SYNCED_BODY: &str = r#"{"data":{"head_slot":"3200","sync_distance":"0","is_syncing":false,"is_optimistic":false,"el_offline":false}}"#;
+
+    fn finality_body(epoch: u64) -> String {
+        format!(
+            r#"{{"data":{{"previous_justified":{{"epoch":"{e}","root":"0x00"}},"current_justified":{{"epoch":"{e}","root":"0x00"}},"finalized":{{"epoch":"{e}","root":"0x00"}}}}}}"#,
+            e = epoch
+        )
+    }
+
+    async fn mock_beacon(
+        server: &mut mockito::ServerGuard,
+        syncing_body: &str,
+        health_status: usize,
+        finalized_epoch: u64,
+    ) {
+        server
+            .mock("GET", "/eth/v1/node/syncing")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(syncing_body)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/eth/v1/node/health")
+            .with_status(health_status)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/eth/v1/beacon/states/head/finality_checkpoints")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(finality_body(finalized_epoch))
+            .create_async()
+            .await;
+    }
+
+    async fn beacon_status(
+        server: &mockito::ServerGuard,
+        max_sync_distance: Option<u64>,
+        max_finality_epochs: Option<u64>,
+    ) -> warp::http::StatusCode {
+        check_beacon(
+            Some(server.url()),
+            max_sync_distance,
+            max_finality_epochs,
+            None,
+        )
+        .await
+        .unwrap()
+        .into_response()
+        .status()
+    }
+
+    #[tokio::test]
+    async fn test_get_beacon_syncing_success() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/eth/v1/node/syncing")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(SYNCED_BODY)
+            .create_async()
+            .await;
+
+        let result = get_beacon_syncing(&server.url()).await;
+        mock.assert_async().await;
+
+        let syncing = result.unwrap();
+        assert_eq!(syncing.head_slot, 3200);
+        assert_eq!(syncing.sync_distance, 0);
+        assert!(!syncing.is_syncing);
+    }
+
+    #[tokio::test]
+    async fn test_get_beacon_syncing_error() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/eth/v1/node/syncing")
+            .with_status(500)
+            .create_async()
+            .await;
+
+        assert!(get_beacon_syncing(&server.url()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_beacon_health_maps_status_codes() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/eth/v1/node/health")
+            .with_status(206)
+            .create_async()
+            .await;
+
+        assert_eq!(get_beacon_health(&server.url()).await.unwrap(), "syncing");
+    }
+
+    #[tokio::test]
+    async fn test_get_finalized_epoch_success() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/eth/v1/beacon/states/head/finality_checkpoints")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(finality_body(98))
+            .create_async()
+            .await;
+
+        assert_eq!(get_finalized_epoch(&server.url()).await.unwrap(), 98);
+    }
+
+    #[tokio::test]
+    async fn test_check_beacon_synced_returns_ok() {
+        // head slot 3200 => epoch 100; finalized 98 => 2 epochs behind.
+        let mut server = Server::new_async().await;
+        mock_beacon(&mut server, SYNCED_BODY, 200, 98).await;
+
+        assert_eq!(
+            beacon_status(&server, None, None).await,
+            warp::http::StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_beacon_finality_lag_returns_unavailable() {
+        let mut server = Server::new_async().await;
+        mock_beacon(&mut server, SYNCED_BODY, 200, 90).await;
+
+        assert_eq!(
+            beacon_status(&server, None, Some(5)).await,
+            warp::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_beacon_sync_distance_exceeded_returns_unavailable() {
+        let body = r#"{"data":{"head_slot":"3200","sync_distance":"10","is_syncing":false}}"#;
+        let mut server = Server::new_async().await;
+        mock_beacon(&mut server, body, 200, 98).await;
+
+        assert_eq!(
+            beacon_status(&server, Some(4), None).await,
+            warp::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_beacon_health_syncing_returns_unavailable() {
+        let mut server = Server::new_async().await;
+        mock_beacon(&mut server, SYNCED_BODY, 206, 98).await;
+
+        assert_eq!(
+            beacon_status(&server, None, None).await,
+            warp::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_beacon_optimistic_or_el_offline_returns_unavailable() {
+        for (body, expected) in [
+            (
+                r#"{"data":{"head_slot":"3200","sync_distance":"0","is_syncing":false,"is_optimistic":true,"el_offline":false}}"#,
+                "optimistic",
+            ),
+            (
+                r#"{"data":{"head_slot":"3200","sync_distance":"0","is_syncing":false,"is_optimistic":true,"el_offline":true}}"#,
+                "el_offline",
+            ),
+        ] {
+            let mut server = Server::new_async().await;
+            mock_beacon(&mut server, body, 200, 98).await;
+
+            let response = check_beacon(Some(server.url()), None, None, None)
+                .await
+                .unwrap()
+                .into_response();
+            assert_eq!(
+                response.status(),
+                warp::http::StatusCode::SERVICE_UNAVAILABLE
+            );
+            let body = warp::hyper::body::to_bytes(response.into_body())
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["status"], expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_beacon_unreachable_returns_error() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/eth/v1/node/syncing")
+            .with_status(500)
+            .create_async()
+            .await;
+
+        assert_eq!(
+            beacon_status(&server, None, None).await,
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_syncing_data_deserialization_without_optional_fields() {
+        let json = r#"{"data":{"head_slot":"1","sync_distance":"2","is_syncing":true}}"#;
+        let parsed: BeaconData<SyncingData> = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.data.head_slot, 1);
+        assert_eq!(parsed.data.sync_distance, 2);
+        assert!(!parsed.data.is_optimistic);
+    }
+}
@@ -0,0 +1,397 @@
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::{
+    get_block_header_by_tag, get_block_headers, parse_hex_u128, rpc_call, BatchRejections,
+    BlockHeader,
+};
+
+/// EIP-4844 constants.
+const GAS_PER_BLOB: u64 = 131_072;
+const MIN_BASE_FEE_PER_BLOB_GAS: u64 = 1;
+const DEFAULT_WINDOW: u64 = 10;
+const MAX_WINDOW: u64 = 128;
+const WEI_PER_GWEI: f64 = 1_000_000_000.0;
+
+#[derive(Serialize)]
+struct BlobResponse {
+    block_number: i64,
+    blob_gas_used: u64,
+    excess_blob_gas: u64,
+    blobs_in_block: u64,
+    blob_base_fee_wei: String,
+    blob_base_fee_gwei: f64,
+    window_blocks: u64,
+    avg_blobs_per_block: f64,
+    alert_threshold_gwei: Option<f64>,
+    status: String,
+}
+
+impl BlobResponse {
+    fn error(status: String) -> Self {
+        BlobResponse {
+            block_number: 0,
+            blob_gas_used: 0,
+            excess_blob_gas: 0,
+            blobs_in_block: 0,
+            blob_base_fee_wei: "0".to_string(),
+            blob_base_fee_gwei: 0.0,
+            window_blocks: 0,
+            avg_blobs_per_block: 0.0,
+            alert_threshold_gwei: None,
+            status,
+        }
+    }
+}
+
+/// `fake_exponential` from EIP-4844: approximates `factor * e ** (numerator / denominator)`
+/// using integer arithmetic exactly as clients do, so the result matches the node.
+fn fake_exponential(factor: u64, numerator: u64, denominator: u64) -> BigUint {
+    let numerator = BigUint::from(numerator);
+    let denominator = BigUint::from(denominator);
+    let mut i = BigUint::from(1u32);
+    let mut output = BigUint::zero();
+    let mut numerator_accum = BigUint::from(factor) * &denominator;
+    while !numerator_accum.is_zero() {
+        output += &numerator_accum;
+        numerator_accum = (numerator_accum * &numerator) / (&denominator * &i);
+        i += 1u32;
+    }
+    output / denominator
+}
+
+/// Blob base fee (wei per blob gas) implied by a block's `excessBlobGas`.
+fn blob_base_fee(excess_blob_gas: u64, update_fraction: u64) -> BigUint {
+    fake_exponential(MIN_BASE_FEE_PER_BLOB_GAS, excess_blob_gas, update_fraction)
+}
+
+/// Blob base fee of block `number` as the node computes it. The update fraction
+/// changes between forks (Cancun 3338477, Prague 5007716), so the node, which knows
+/// its schedule, is asked instead of assuming one.
+async fn get_node_blob_base_fee(
+    rpc_url: Option<String>,
+    number: i64,
+) -> Result<BigUint, Box<dyn std::error::Error>> {
+    let history = rpc_call(
+        rpc_url,
+        "eth_feeHistory",
+        vec![
+            Value::String("0x1".to_string()),
+            Value::String(format!("0x{:x}", number)),
+            Value::Array(vec![]),
+        ],
+    )
+    .await?;
+    // The first entry is the fee of the requested block, the second that of the next.
+    let fee = history
+        .get("baseFeePerBlobGas")
+        .and_then(|v| v.as_array())
+        .and_then(|v| v.first())
+        .and_then(|v| v.as_str())
+        .ok_or(
+            "eth_feeHistory returned no baseFeePerBlobGas; pass update_fraction to compute the fee locally",
+        )?;
+    Ok(BigUint::from(parse_hex_u128(fee)?))
+}
+
+fn blob_fields(header: &BlockHeader) -> Result<(u64, u64), String> {
+    match (header.blob_gas_used, header.excess_blob_gas) {
+        (Some(used), Some(excess)) => Ok((used, excess)),
+        _ => Err(format!(
+            "block {} has no blobGasUsed/excessBlobGas (pre-Cancun or non-blob chain)",
+            header.number
+        )),
+    }
+}
+
+/// HTTP handler for `/blob`. Reads `blobGasUsed` and `excessBlobGas` from the latest
+/// block and the `window - 1` blocks before it, reports the blob base fee and the
+/// average number of blobs per block, and returns 503 when the blob base fee is above
+/// `max_blob_fee` gwei. The fee comes from the node unless `update_fraction` is given,
+/// in which case it is computed from `excessBlobGas`.
+pub async fn check_blobs(
+    rpc_url: Option<String>,
+    window: Option<u64>,
+    max_blob_fee: Option<f64>,
+    update_fraction: Option<u64>,
+    batch_rejections: Arc<BatchRejections>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let window = window
+        .filter(|w| *w > 0)
+        .unwrap_or(DEFAULT_WINDOW)
+        .min(MAX_WINDOW);
+
+    let latest = match get_block_header_by_tag(rpc_url.clone(), "latest").await {
+        Ok(h) => h,
+        Err(e) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&BlobResponse::error(format!("error: {}", e))),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+
+    let (blob_gas_used, excess_blob_gas) = match blob_fields(&latest) {
+        Ok(fields) => fields,
+        Err(e) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&BlobResponse::error(format!("error: {}", e))),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+
+    // The blocks before the latest, fetched in one batch; the window shrinks near
+    // genesis.
+    let earlier: Vec<String> = (1..window)
+        .map(|offset| latest.number - offset as i64)
+        .take_while(|number| *number >= 0)
+        .map(|number| format!("0x{:x}", number))
+        .collect();
+    let headers = match get_block_headers(rpc_url.clone(), &earlier, &batch_rejections).await {
+        Ok(h) => h,
+        Err(e) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&BlobResponse::error(format!("error: {}", e))),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+    // Blocks before the fork simply carried no blobs.
+    let total_blobs = blob_gas_used / GAS_PER_BLOB
+        + headers
+            .iter()
+            .map(|h| h.blob_gas_used.unwrap_or(0) / GAS_PER_BLOB)
+            .sum::<u64>();
+    let window_blocks = 1 + headers.len() as u64;
+
+    let fee = match update_fraction.filter(|f| *f > 0) {
+        Some(fraction) => blob_base_fee(excess_blob_gas, fraction),
+        None => match get_node_blob_base_fee(rpc_url, latest.number).await {
+            Ok(fee) => fee,
+            Err(e) => {
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&BlobResponse::error(format!("error: {}", e))),
+                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+                ));
+            }
+        },
+    };
+    let fee_gwei = fee.to_f64().unwrap_or(f64::MAX) / WEI_PER_GWEI;
+    let fee_high = max_blob_fee.map(|max| fee_gwei > max).unwrap_or(false);
+
+    let response = BlobResponse {
+        block_number: latest.number,
+        blob_gas_used,
+        excess_blob_gas,
+        blobs_in_block: blob_gas_used / GAS_PER_BLOB,
+        blob_base_fee_wei: fee.to_string(),
+        blob_base_fee_gwei: fee_gwei,
+        window_blocks,
+        avg_blobs_per_block: total_blobs as f64 / window_blocks as f64,
+        alert_threshold_gwei: max_blob_fee,
+        status: if fee_high {
+            "blob_fee_high".to_string()
+        } else {
+            "blob_fee_normal".to_string()
+        },
+    };
+
+    if fee_high {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    } else {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::rpc_node;
+    use mockito::Server;
+    use std::collections::HashMap;
+    use warp::Reply;
+
+    fn block_body(number: u64, blob_gas_used: u64, excess_blob_gas: u64) -> String {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":1,"result":{{"number":"0x{:x}","timestamp":"0x1","blobGasUsed":"0x{:x}","excessBlobGas":"0x{:x}"}}}}"#,
+            number, blob_gas_used, excess_blob_gas
+        )
+    }
+
+    /// `BLOB_BASE_FEE_UPDATE_FRACTION` since Prague (EIP-7691).
+    const PRAGUE_UPDATE_FRACTION: u64 = 5_007_716;
+
+    /// Serves `eth_getBlockByNumber` for each `(tag, body)` pair, singly or batched,
+    /// and `eth_feeHistory` with `fee_history` as its result.
+    async fn mock_blocks(
+        server: &mut mockito::ServerGuard,
+        blocks: Vec<(&str, String)>,
+        fee_history: &str,
+    ) -> mockito::Mock {
+        let fee_history = format!(r#"{{"jsonrpc":"2.0","id":1,"result":{}}}"#, fee_history);
+        let blocks: HashMap<String, String> = blocks
+            .into_iter()
+            .map(|(tag, body)| (tag.to_string(), body))
+            .collect();
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(rpc_node(move |req| {
+                if req["method"] == "eth_feeHistory" {
+                    return fee_history.clone();
+                }
+                blocks[req["params"][0].as_str().unwrap()].clone()
+            }))
+            .create_async()
+            .await
+    }
+
+    #[test]
+    fn test_blob_base_fee_at_zero_excess_is_minimum() {
+        assert_eq!(
+            blob_base_fee(0, PRAGUE_UPDATE_FRACTION),
+            BigUint::from(1u32)
+        );
+    }
+
+    #[test]
+    fn test_fake_exponential_matches_eip_vectors() {
+        // Test vectors from the EIP-4844 reference implementation.
+        assert_eq!(fake_exponential(1, 0, 1), BigUint::from(1u32));
+        assert_eq!(fake_exponential(38493, 0, 1000), BigUint::from(38493u32));
+        assert_eq!(fake_exponential(1, 2, 1), BigUint::from(6u32));
+        assert_eq!(fake_exponential(1, 4, 2), BigUint::from(6u32));
+        assert_eq!(fake_exponential(1, 3, 1), BigUint::from(16u32));
+        assert_eq!(fake_exponential(1, 6, 2), BigUint::from(18u32));
+        assert_eq!(fake_exponential(1, 4, 1), BigUint::from(49u32));
+        assert_eq!(
+            fake_exponential(1, 50_000_000, 2_225_652),
+            BigUint::from(5_709_098_764u64)
+        );
+    }
+
+    async fn blob_json(
+        server: &mockito::ServerGuard,
+        window: Option<u64>,
+        max_blob_fee: Option<f64>,
+        update_fraction: Option<u64>,
+    ) -> (warp::http::StatusCode, serde_json::Value) {
+        let response = check_blobs(
+            Some(server.url()),
+            window,
+            max_blob_fee,
+            update_fraction,
+            Default::default(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let status = response.status();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_check_blobs_averages_window() {
+        let mut server = Server::new_async().await;
+        mock_blocks(
+            &mut server,
+            vec![
+                ("latest", block_body(100, 3 * GAS_PER_BLOB, 0)),
+                ("0x63", block_body(99, 6 * GAS_PER_BLOB, 0)),
+                ("0x62", block_body(98, 0, 0)),
+            ],
+            r#"{"baseFeePerBlobGas":["0x1","0x1"]}"#,
+        )
+        .await;
+
+        let (status, json) = blob_json(&server, Some(3), None, None).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(json["blobs_in_block"], 3);
+        assert_eq!(json["window_blocks"], 3);
+        assert_eq!(json["avg_blobs_per_block"], 3.0);
+        assert_eq!(json["blob_base_fee_wei"], "1");
+    }
+
+    #[tokio::test]
+    async fn test_check_blobs_fee_above_threshold_returns_unavailable() {
+        // The node reports a 20 gwei blob base fee for the latest block.
+        let mut server = Server::new_async().await;
+        mock_blocks(
+            &mut server,
+            vec![("latest", block_body(100, GAS_PER_BLOB, 120_000_000))],
+            r#"{"baseFeePerBlobGas":["0x4a817c800","0x4a817c800"]}"#,
+        )
+        .await;
+
+        let (status, json) = blob_json(&server, Some(1), Some(1.0), None).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["blob_base_fee_wei"], "20000000000");
+    }
+
+    #[tokio::test]
+    async fn test_check_blobs_update_fraction_computes_fee_locally() {
+        // Enough excess blob gas to push the fee to ~tens of gwei; the node's fee
+        // history is not consulted.
+        let mut server = Server::new_async().await;
+        mock_blocks(
+            &mut server,
+            vec![("latest", block_body(100, GAS_PER_BLOB, 120_000_000))],
+            r#"{"baseFeePerBlobGas":["0x1","0x1"]}"#,
+        )
+        .await;
+
+        let (status, json) =
+            blob_json(&server, Some(1), Some(1.0), Some(PRAGUE_UPDATE_FRACTION)).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            json["blob_base_fee_wei"],
+            blob_base_fee(120_000_000, PRAGUE_UPDATE_FRACTION).to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_blobs_without_node_blob_fee_returns_error() {
+        let mut server = Server::new_async().await;
+        mock_blocks(
+            &mut server,
+            vec![("latest", block_body(100, GAS_PER_BLOB, 0))],
+            r#"{"oldestBlock":"0x64","baseFeePerGas":["0x1","0x1"],"gasUsedRatio":[0.5]}"#,
+        )
+        .await;
+
+        let (status, json) = blob_json(&server, Some(1), None, None).await;
+        assert_eq!(status, warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(json["status"].as_str().unwrap().contains("update_fraction"));
+    }
+
+    #[tokio::test]
+    async fn test_check_blobs_pre_cancun_block_returns_error() {
+        let mut server = Server::new_async().await;
+        mock_blocks(
+            &mut server,
+            vec![(
+                "latest",
+                r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0x10"}}"#.to_string(),
+            )],
+            "{}",
+        )
+        .await;
+
+        let (status, _) = blob_json(&server, None, None, None).await;
+        assert_eq!(status, warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
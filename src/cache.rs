@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::OnceCell;
+use tokio::time::Duration;
+use warp::http::{HeaderMap, HeaderValue, Method, Request, Response, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::hyper::service::Service;
+use warp::hyper::Body;
+
+/// Endpoints whose responses come from RPC calls and are worth caching.
+const CACHEABLE_PATHS: [&str; 10] = [
+    "/",
+    "/finalized_latest_diff",
+    "/check_balance",
+    "/beacon_status",
+    "/gas",
+    "/blob",
+    "/txpool",
+    "/assertion",
+    "/proposal_lag",
+    "/batch_lag",
+];
+
+/// Entries kept before expired ones are swept.
+const SWEEP_ABOVE: usize = 1_024;
+/// Hard cap on entries: every distinct query string is a key, so callers could
+/// otherwise grow the cache without bound within one TTL.
+const MAX_ENTRIES: usize = 4_096;
+
+struct Cached {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: Instant,
+}
+
+impl Cached {
+    fn response(&self, hit: bool) -> Response<Body> {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response.headers_mut().insert(
+            "x-cache",
+            HeaderValue::from_static(if hit { "hit" } else { "miss" }),
+        );
+        response
+    }
+}
+
+/// Short-lived cache of RPC-backed responses, keyed by path and query string
+/// (i.e. by check and target). Concurrent requests for a key that is not cached
+/// yet wait for a single upstream call instead of each making their own.
+pub struct ResponseCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Arc<OnceCell<Cached>>>>,
+}
+
+impl ResponseCache {
+    /// A zero `ttl` disables caching.
+    pub fn new(ttl: Duration) -> Self {
+        ResponseCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn key(&self, req: &Request<Body>) -> Option<String> {
+        if self.ttl.is_zero()
+            || req.method() != Method::GET
+            || !CACHEABLE_PATHS.contains(&req.uri().path())
+        {
+            return None;
+        }
+        Some(
+            req.uri()
+                .path_and_query()
+                .map(|p| p.as_str().to_string())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// The slot for `key`: the in-flight or fresh one, or a new one replacing an
+    /// expired entry. At `MAX_ENTRIES` the oldest stored entry is evicted, or an
+    /// in-flight one when there is none; its waiters keep their handle on the slot.
+    fn slot(&self, key: String) -> Arc<OnceCell<Cached>> {
+        let fresh = |cell: &OnceCell<Cached>| match cell.get() {
+            Some(cached) => cached.stored_at.elapsed() < self.ttl,
+            None => true,
+        };
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(cell) = entries.get(&key).filter(|c| fresh(c)) {
+            return cell.clone();
+        }
+        if entries.len() >= SWEEP_ABOVE {
+            entries.retain(|_, cell| fresh(cell));
+        }
+        if entries.len() >= MAX_ENTRIES {
+            // Completed entries go first, oldest first.
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, cell)| match cell.get() {
+                    Some(cached) => (false, Some(cached.stored_at)),
+                    None => (true, None),
+                })
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        let cell = Arc::new(OnceCell::new());
+        entries.insert(key, cell.clone());
+        cell
+    }
+
+    /// Answer `req` from the cache when possible, otherwise from `service`.
+    pub async fn serve<S>(
+        &self,
+        mut service: S,
+        req: Request<Body>,
+    ) -> Result<Response<Body>, Infallible>
+    where
+        S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>,
+    {
+        let key = match self.key(&req) {
+            Some(key) => key,
+            None => return service.call(req).await,
+        };
+
+        let slot = self.slot(key);
+        let mut hit = true;
+        let cached = slot
+            .get_or_init(|| async {
+                hit = false;
+                let response = match service.call(req).await {
+                    Ok(response) => response,
+                    Err(never) => match never {},
+                };
+                let (parts, body) = response.into_parts();
+                let body = warp::hyper::body::to_bytes(body).await.unwrap_or_default();
+                Cached {
+                    status: parts.status,
+                    headers: parts.headers,
+                    body,
+                    stored_at: Instant::now(),
+                }
+            })
+            .await;
+        Ok(cached.response(hit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use warp::Filter;
+
+    fn counting_service(
+        calls: Arc<AtomicUsize>,
+    ) -> impl Service<Request<Body>, Response = Response<Body>, Error = Infallible> + Clone {
+        warp::service(warp::any().and_then(move || {
+            let calls = calls.clone();
+            async move {
+                let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok::<_, Infallible>(n.to_string())
+            }
+        }))
+    }
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::builder().uri(uri).body(Body::empty()).unwrap()
+    }
+
+    async fn body(response: Response<Body>) -> String {
+        let bytes = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_share_one_upstream_call() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        let service = counting_service(calls.clone());
+
+        let requests =
+            (0..5).map(|_| cache.serve(service.clone(), get("/check_balance?address=0x1")));
+        let mut hits = 0;
+        for response in futures_util::future::join_all(requests).await {
+            let response = response.unwrap();
+            if response.headers()["x-cache"] == "hit" {
+                hits += 1;
+            }
+            assert_eq!(body(response).await, "1");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(hits, 4);
+
+        // A different target or check is a different key.
+        let response = cache
+            .serve(service.clone(), get("/check_balance?address=0x2"))
+            .await
+            .unwrap();
+        assert_eq!(body(response).await, "2");
+    }
+
+    #[tokio::test]
+    async fn test_expired_and_uncacheable_requests_go_upstream() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = ResponseCache::new(Duration::from_millis(10));
+        let service = counting_service(calls.clone());
+
+        cache.serve(service.clone(), get("/gas")).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let response = cache.serve(service.clone(), get("/gas")).await.unwrap();
+        assert_eq!(body(response).await, "2");
+
+        cache.serve(service.clone(), get("/health")).await.unwrap();
+        cache.serve(service.clone(), get("/health")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_entries_are_capped() {
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        let cached = |stored_at| Cached {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+            stored_at,
+        };
+        let now = Instant::now();
+        let _ = cache
+            .slot("/gas?blocks=0".to_string())
+            .set(cached(now - Duration::from_secs(1)));
+        for i in 1..MAX_ENTRIES {
+            let _ = cache.slot(format!("/gas?blocks={}", i)).set(cached(now));
+        }
+
+        cache.slot("/gas?blocks=new".to_string());
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert!(!entries.contains_key("/gas?blocks=0"));
+        assert!(entries.contains_key("/gas?blocks=new"));
+    }
+
+    #[tokio::test]
+    async fn test_zero_ttl_disables_cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = ResponseCache::new(Duration::ZERO);
+        let service = counting_service(calls.clone());
+        cache.serve(service.clone(), get("/gas")).await.unwrap();
+        cache.serve(service.clone(), get("/gas")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,661 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+
+use crate::hysteresis::Damping;
+
+/// Monitor configuration, loaded from the JSON file named by `MONITOR_CONFIG`.
+/// The query-parameter endpoints work without it; configured checks need it.
+#[derive(Deserialize, Clone, Default)]
+pub struct Config {
+    /// Seconds between background poller rounds.
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    #[serde(default)]
+    pub targets: Vec<Target>,
+    #[serde(default)]
+    pub notifiers: Vec<Notifier>,
+    /// Persist every poller result when set.
+    #[serde(default)]
+    pub history: Option<HistoryConfig>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct HistoryConfig {
+    /// SQLite database file, created if missing.
+    #[serde(default = "default_history_path")]
+    pub path: String,
+    /// Results older than this are deleted; 0 keeps everything.
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u64,
+}
+
+fn default_history_path() -> String {
+    "monitor-history.db".to_string()
+}
+
+fn default_retention_days() -> u64 {
+    30
+}
+
+fn default_poll_interval_secs() -> u64 {
+    30
+}
+
+fn default_head_stall_secs() -> u64 {
+    60
+}
+
+/// A monitored node and the checks configured against it.
+#[derive(Deserialize, Clone)]
+pub struct Target {
+    pub name: String,
+    pub rpc: String,
+    /// Chain the target belongs to, for `/health?chain=`.
+    #[serde(default)]
+    pub chain: Option<String>,
+    /// Free-form labels, for `/health?tags=`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub checks: Vec<Check>,
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    #[serde(default)]
+    pub log_watchers: Vec<LogWatcher>,
+    #[serde(default)]
+    pub kuma: Option<KumaPush>,
+    /// `ws://` or `wss://` endpoint. When set, heads arrive over an `eth_subscribe`
+    /// `newHeads` subscription and the `sync` check keys off their arrival time.
+    #[serde(default)]
+    pub ws: Option<String>,
+    /// Seconds without a new head before a subscribed target's `sync` check fails.
+    #[serde(default = "default_head_stall_secs")]
+    pub head_stall_secs: u64,
+}
+
+/// Uptime Kuma push monitors fed with this target's poller results.
+#[derive(Deserialize, Clone)]
+pub struct KumaPush {
+    /// Kuma base URL, e.g. `http://kuma:3001`.
+    pub url: String,
+    /// Check name (e.g. `sync`, `balance:0x…`, `assertion:<name>`) to push token.
+    pub tokens: HashMap<String, String>,
+}
+
+/// How much a failing check counts towards the aggregate `/health` status.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Critical,
+    Warning,
+}
+
+/// A built-in check run against the target by the background poller.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Check {
+    #[serde(flatten)]
+    pub kind: CheckConfig,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+impl Check {
+    pub fn name(&self) -> String {
+        self.kind.name()
+    }
+}
+
+/// The kind-specific settings of a built-in check.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CheckConfig {
+    /// The head block must advance between poller rounds.
+    Sync,
+    /// Same semantics as `/finalized_latest_diff?diff=&warn=`.
+    FinalizedLatestDiff {
+        diff: i64,
+        #[serde(default)]
+        warn: Option<i64>,
+        #[serde(flatten)]
+        damping: Damping,
+    },
+    /// Same semantics as `/check_balance?address=&alert=&warn=`.
+    Balance {
+        address: String,
+        #[serde(default)]
+        alert: Option<String>,
+        #[serde(default)]
+        warn: Option<String>,
+        #[serde(flatten)]
+        damping: Damping,
+    },
+}
+
+impl CheckConfig {
+    /// Stable check name used in results and alerts.
+    pub fn name(&self) -> String {
+        match self {
+            CheckConfig::Sync => "sync".to_string(),
+            CheckConfig::FinalizedLatestDiff { .. } => "finalized_latest_diff".to_string(),
+            CheckConfig::Balance { address, .. } => format!("balance:{}", address),
+        }
+    }
+}
+
+/// An outgoing webhook fired when a polled check changes state.
+#[derive(Deserialize, Clone)]
+pub struct Notifier {
+    /// Webhook URL, or the PagerDuty Events v2 / Opsgenie alerts API endpoint.
+    pub url: String,
+    #[serde(default)]
+    pub format: NotifierFormat,
+    /// Repeat a still-firing alert after this many minutes; 0 disables re-notification.
+    #[serde(default = "default_renotify_minutes")]
+    pub renotify_minutes: u64,
+    /// Only notify for these checks (exact name, or the part before `:`); empty means all.
+    #[serde(default)]
+    pub checks: Vec<String>,
+    /// PagerDuty integration routing key.
+    #[serde(default)]
+    pub routing_key: Option<String>,
+    /// Opsgenie API key, sent as `Authorization: GenieKey <key>`.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Incident severity for `pagerduty` (`critical`, `error`, `warning`, `info`).
+    #[serde(default = "default_severity")]
+    pub severity: String,
+}
+
+fn default_severity() -> String {
+    "critical".to_string()
+}
+
+impl Notifier {
+    /// Whether results of `check` should go to this notifier.
+    pub fn wants(&self, check: &str) -> bool {
+        let kind = check.split(':').next().unwrap_or(check);
+        self.checks.is_empty() || self.checks.iter().any(|c| c == check || c == kind)
+    }
+}
+
+fn default_renotify_minutes() -> u64 {
+    60
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifierFormat {
+    /// The check result as generic JSON.
+    #[default]
+    Json,
+    /// Slack incoming-webhook `{"text": ...}`.
+    Slack,
+    /// Discord webhook `{"content": ...}`.
+    Discord,
+    /// PagerDuty Events API v2 trigger/resolve events.
+    #[serde(rename = "pagerduty")]
+    PagerDuty,
+    /// Opsgenie alert create/close requests.
+    Opsgenie,
+}
+
+/// An `eth_call` whose result must satisfy `expect`.
+#[derive(Deserialize, Clone)]
+pub struct Assertion {
+    pub name: String,
+    pub contract: String,
+    /// Solidity function signature, e.g. `paused()` or `balanceOf(address)`.
+    pub signature: String,
+    /// ABI-encoded arguments appended after the selector, as hex.
+    #[serde(default)]
+    pub args: String,
+    pub expect: Expectation,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Expectation {
+    /// `value` is a decimal or `0x` hex integer, `true`/`false`, or an address.
+    Equals {
+        value: String,
+    },
+    GreaterThan {
+        value: String,
+    },
+    LessThan {
+        value: String,
+    },
+    /// The result is a unix timestamp that must be at most `seconds` old.
+    MaxAge {
+        seconds: u64,
+    },
+}
+
+/// Counts logs matching `event` (and optional indexed-topic filters) emitted by
+/// `contract`, and alerts on the number seen within the last `window_minutes`.
+#[derive(Deserialize, Clone)]
+pub struct LogWatcher {
+    pub name: String,
+    pub contract: String,
+    /// Event signature, e.g. `OwnershipTransferred(address,address)`.
+    pub event: String,
+    /// Filters for indexed topics 1..=3 as 32-byte hex; `null` matches anything.
+    #[serde(default)]
+    pub topics: Vec<Option<String>>,
+    #[serde(default = "default_window_minutes")]
+    pub window_minutes: u64,
+    /// Blocks below the head left unscanned until they are this deep, so a reorg
+    /// does not drop or duplicate events.
+    #[serde(default = "default_confirmations")]
+    pub confirmations: u64,
+    pub alert: LogAlert,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+fn default_window_minutes() -> u64 {
+    60
+}
+
+fn default_confirmations() -> u64 {
+    3
+}
+
+/// A 32-byte topic as `0x`-prefixed hex.
+fn is_valid_topic(topic: &str) -> bool {
+    match topic
+        .strip_prefix("0x")
+        .or_else(|| topic.strip_prefix("0X"))
+    {
+        Some(hex) => hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LogAlert {
+    /// Fire when no matching event was seen within the window.
+    Absence,
+    /// Fire when more than `max_events` matching events were seen within the window.
+    MaxRate { max_events: u64 },
+    /// Fire while any matching event was seen within the window.
+    AnyOccurrence,
+}
+
+impl Config {
+    pub fn target(&self, name: &str) -> Option<&Target> {
+        self.targets.iter().find(|t| t.name == name)
+    }
+}
+
+impl Target {
+    pub fn assertion(&self, name: &str) -> Option<&Assertion> {
+        self.assertions.iter().find(|a| a.name == name)
+    }
+
+    pub fn log_watcher(&self, name: &str) -> Option<&LogWatcher> {
+        self.log_watchers.iter().find(|w| w.name == name)
+    }
+
+    /// Names and severities of every check the poller runs against this target, in
+    /// config order.
+    pub fn check_severities(&self) -> Vec<(String, Severity)> {
+        let checks = self.checks.iter().map(|c| (c.name(), c.severity));
+        let assertions = self
+            .assertions
+            .iter()
+            .map(|a| (format!("assertion:{}", a.name), a.severity));
+        let watchers = self
+            .log_watchers
+            .iter()
+            .map(|w| (format!("log_watcher:{}", w.name), w.severity));
+        checks.chain(assertions).chain(watchers).collect()
+    }
+
+    pub fn check_names(&self) -> Vec<String> {
+        self.check_severities()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+/// Parse and validate a configuration document.
+pub fn parse_config(contents: &str) -> Result<Config, Box<dyn std::error::Error>> {
+    let config: Config = serde_json::from_str(contents)?;
+
+    for target in &config.targets {
+        if config
+            .targets
+            .iter()
+            .filter(|t| t.name == target.name)
+            .count()
+            > 1
+        {
+            return Err(format!("duplicate target name '{}'", target.name).into());
+        }
+        if let Some(ws) = &target.ws {
+            if !ws.starts_with("ws://") && !ws.starts_with("wss://") {
+                return Err(format!(
+                    "target '{}': ws must be a ws:// or wss:// url, got '{}'",
+                    target.name, ws
+                )
+                .into());
+            }
+        }
+        for check in &target.checks {
+            if let CheckConfig::Balance { address, .. } = &check.kind {
+                if !crate::is_valid_eth_address(address) {
+                    return Err(format!(
+                        "target '{}' balance check: invalid address '{}'",
+                        target.name, address
+                    )
+                    .into());
+                }
+            }
+        }
+        for assertion in &target.assertions {
+            if !crate::is_valid_eth_address(&assertion.contract) {
+                return Err(format!(
+                    "target '{}' assertion '{}': invalid contract address '{}'",
+                    target.name, assertion.name, assertion.contract
+                )
+                .into());
+            }
+        }
+        for watcher in &target.log_watchers {
+            if !crate::is_valid_eth_address(&watcher.contract) {
+                return Err(format!(
+                    "target '{}' log watcher '{}': invalid contract address '{}'",
+                    target.name, watcher.name, watcher.contract
+                )
+                .into());
+            }
+            if watcher.topics.len() > 3 {
+                return Err(format!(
+                    "target '{}' log watcher '{}': at most 3 indexed topics",
+                    target.name, watcher.name
+                )
+                .into());
+            }
+            if let Some(topic) = watcher.topics.iter().flatten().find(|t| !is_valid_topic(t)) {
+                return Err(format!(
+                    "target '{}' log watcher '{}': topic '{}' is not 32-byte hex",
+                    target.name, watcher.name, topic
+                )
+                .into());
+            }
+        }
+    }
+
+    for notifier in &config.notifiers {
+        match notifier.format {
+            NotifierFormat::PagerDuty if notifier.routing_key.is_none() => {
+                return Err(
+                    format!("pagerduty notifier '{}' needs a routing_key", notifier.url).into(),
+                )
+            }
+            NotifierFormat::Opsgenie if notifier.api_key.is_none() => {
+                return Err(format!("opsgenie notifier '{}' needs an api_key", notifier.url).into())
+            }
+            _ => {}
+        }
+    }
+
+    if config.poll_interval_secs == 0 {
+        return Err("poll_interval_secs must be positive".into());
+    }
+
+    Ok(config)
+}
+
+/// Load the configuration named by `MONITOR_CONFIG`, or an empty one when unset.
+/// A set-but-unreadable or invalid file is an error: silently starting with no
+/// checks would look healthy while monitoring nothing.
+pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
+    match env::var("MONITOR_CONFIG") {
+        Ok(path) => {
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| format!("failed to read config '{}': {}", path, e))?;
+            parse_config(&contents).map_err(|e| format!("invalid config '{}': {}", path, e).into())
+        }
+        Err(_) => Ok(Config::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "targets": [{
+            "name": "op-mainnet",
+            "rpc": "http://127.0.0.1:8545",
+            "assertions": [{
+                "name": "bridge_not_paused",
+                "contract": "0x0000000000000000000000000000000000000001",
+                "signature": "paused()",
+                "expect": {"op": "equals", "value": "false"}
+            }]
+        }]
+    }"#;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config(CONFIG).unwrap();
+        let target = config.target("op-mainnet").unwrap();
+        assert_eq!(target.rpc, "http://127.0.0.1:8545");
+        let assertion = target.assertion("bridge_not_paused").unwrap();
+        assert_eq!(assertion.args, "");
+        assert_eq!(
+            assertion.expect,
+            Expectation::Equals {
+                value: "false".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_target_check_names() {
+        let config = CONFIG.replace(
+            r#""rpc": "http://127.0.0.1:8545","#,
+            r#""rpc": "http://127.0.0.1:8545", "checks": [{"kind": "sync"}],"#,
+        );
+        let config = parse_config(&config).unwrap();
+        assert_eq!(
+            config.targets[0].check_names(),
+            vec!["sync", "assertion:bridge_not_paused"]
+        );
+    }
+
+    #[test]
+    fn test_parse_config_chain_tags_and_severity() {
+        let config = r#"{"targets":[{"name":"l1","rpc":"http://x","chain":"ethereum",
+            "tags":["prod","l1"],
+            "checks":[{"kind":"sync"},{"kind":"finalized_latest_diff","diff":64,"severity":"warning"}]}]}"#;
+        let config = parse_config(config).unwrap();
+        let target = &config.targets[0];
+        assert_eq!(target.chain.as_deref(), Some("ethereum"));
+        assert_eq!(target.tags, vec!["prod", "l1"]);
+        assert_eq!(
+            target.check_severities(),
+            vec![
+                ("sync".to_string(), Severity::Critical),
+                ("finalized_latest_diff".to_string(), Severity::Warning)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_config_rejects_invalid_contract() {
+        let config = CONFIG.replace("0x0000000000000000000000000000000000000001", "0x01");
+        assert!(parse_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_parse_config_rejects_duplicate_targets() {
+        let config = r#"{"targets":[{"name":"a","rpc":"http://x"},{"name":"a","rpc":"http://y"}]}"#;
+        assert!(parse_config(config).is_err());
+    }
+
+    #[test]
+    fn test_parse_config_log_watcher_defaults() {
+        let config = r#"{"targets":[{"name":"l1","rpc":"http://x","log_watchers":[{
+            "name":"deposits",
+            "contract":"0x0000000000000000000000000000000000000001",
+            "event":"DepositFinalized(address,address,address,address,uint256,bytes)",
+            "alert":{"kind":"absence"}}]}]}"#;
+        let config = parse_config(config).unwrap();
+        assert_eq!(config.poll_interval_secs, 30);
+        let watcher = config.targets[0].log_watcher("deposits").unwrap();
+        assert_eq!(watcher.window_minutes, 60);
+        assert_eq!(watcher.confirmations, 3);
+        assert!(watcher.topics.is_empty());
+        assert_eq!(watcher.alert, LogAlert::Absence);
+    }
+
+    #[test]
+    fn test_parse_config_rejects_too_many_topics() {
+        let config = r#"{"targets":[{"name":"l1","rpc":"http://x","log_watchers":[{
+            "name":"w",
+            "contract":"0x0000000000000000000000000000000000000001",
+            "event":"E()",
+            "topics":[null,null,null,null],
+            "alert":{"kind":"any_occurrence"}}]}]}"#;
+        assert!(parse_config(config).is_err());
+    }
+
+    #[test]
+    fn test_parse_config_rejects_malformed_topics() {
+        let watcher = |topic: &str| {
+            format!(
+                r#"{{"targets":[{{"name":"l1","rpc":"http://x","log_watchers":[{{
+                "name":"w",
+                "contract":"0x0000000000000000000000000000000000000001",
+                "event":"E(address)",
+                "topics":[null,"{}"],
+                "alert":{{"kind":"any_occurrence"}}}}]}}]}}"#,
+                topic
+            )
+        };
+        let valid = format!("0x{}", "00".repeat(31) + "ab");
+        assert!(parse_config(&watcher(&valid)).is_ok());
+        for topic in [
+            "0x01",
+            "ab".repeat(32).as_str(),
+            &format!("0x{}", "zz".repeat(32)),
+        ] {
+            let err = parse_config(&watcher(topic)).err().unwrap();
+            assert!(err.to_string().contains("not 32-byte hex"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_parse_config_checks_and_notifiers() {
+        let config = r#"{
+            "targets":[{"name":"l1","rpc":"http://x","checks":[
+                {"kind":"sync"},
+                {"kind":"finalized_latest_diff","diff":64},
+                {"kind":"balance","address":"0x0000000000000000000000000000000000000001","alert":"100"}]}],
+            "notifiers":[{"url":"http://127.0.0.1:9000/hook","format":"slack"},{"url":"http://y"}]}"#;
+        let config = parse_config(config).unwrap();
+        let names: Vec<String> = config.targets[0].checks.iter().map(|c| c.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "sync",
+                "finalized_latest_diff",
+                "balance:0x0000000000000000000000000000000000000001"
+            ]
+        );
+        assert_eq!(config.targets[0].checks[1].severity, Severity::Critical);
+        assert_eq!(
+            config.targets[0].checks[1].kind,
+            CheckConfig::FinalizedLatestDiff {
+                diff: 64,
+                warn: None,
+                damping: Damping::default(),
+            }
+        );
+        assert_eq!(config.notifiers[0].format, NotifierFormat::Slack);
+        assert_eq!(config.notifiers[1].format, NotifierFormat::Json);
+        assert_eq!(config.notifiers[1].renotify_minutes, 60);
+    }
+
+    #[test]
+    fn test_parse_config_incident_notifiers() {
+        let config = r#"{"notifiers":[{
+            "url":"https://events.pagerduty.com/v2/enqueue",
+            "format":"pagerduty",
+            "routing_key":"abc",
+            "checks":["sync","balance"]}]}"#;
+        let config = parse_config(config).unwrap();
+        let notifier = &config.notifiers[0];
+        assert_eq!(notifier.format, NotifierFormat::PagerDuty);
+        assert_eq!(notifier.severity, "critical");
+        assert!(notifier.wants("sync"));
+        assert!(notifier.wants("balance:0x0000000000000000000000000000000000000001"));
+        assert!(!notifier.wants("log_watcher:deposits"));
+
+        let missing_key = r#"{"notifiers":[{"url":"http://x","format":"opsgenie"}]}"#;
+        assert!(parse_config(missing_key).is_err());
+    }
+
+    #[test]
+    fn test_parse_config_check_damping() {
+        let config = r#"{"targets":[{"name":"l1","rpc":"http://x","checks":[
+            {"kind":"finalized_latest_diff","diff":64,"warn":32,"fail_after":3,"margin_pct":10}]}]}"#;
+        let config = parse_config(config).unwrap();
+        assert_eq!(
+            config.targets[0].checks[0].kind,
+            CheckConfig::FinalizedLatestDiff {
+                diff: 64,
+                warn: Some(32),
+                damping: Damping {
+                    fail_after: 3,
+                    recover_after: 1,
+                    margin_pct: 10,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_config_kuma_tokens() {
+        let config = r#"{"targets":[{"name":"l1","rpc":"http://x",
+            "kuma":{"url":"http://kuma:3001","tokens":{"sync":"abc"}}}]}"#;
+        let config = parse_config(config).unwrap();
+        let kuma = config.targets[0].kuma.as_ref().unwrap();
+        assert_eq!(kuma.tokens["sync"], "abc");
+        assert!(config.targets[0].checks.is_empty());
+    }
+
+    #[test]
+    fn test_parse_config_history_defaults() {
+        let config = parse_config(r#"{"history":{}}"#).unwrap();
+        let history = config.history.unwrap();
+        assert_eq!(history.path, "monitor-history.db");
+        assert_eq!(history.retention_days, 30);
+        assert!(parse_config("{}").unwrap().history.is_none());
+    }
+
+    #[test]
+    fn test_parse_config_rejects_invalid_balance_address() {
+        let config = r#"{"targets":[{"name":"l1","rpc":"http://x","checks":[
+            {"kind":"balance","address":"0x01"}]}]}"#;
+        assert!(parse_config(config).is_err());
+    }
+
+    #[test]
+    fn test_parse_config_max_age_expectation() {
+        let json = r#"{"op":"max_age","seconds":3600}"#;
+        let expect: Expectation = serde_json::from_str(json).unwrap();
+        assert_eq!(expect, Expectation::MaxAge { seconds: 3600 });
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::state::{CheckEntry, CheckResult, MonitorState};
+
+/// Detail fields shown as a check's value, in order of preference.
+const VALUE_KEYS: [&str; 8] = [
+    "difference",
+    "balance_decimal",
+    "block_number_decimal",
+    "events_in_window",
+    "seconds_since_submission",
+    "value",
+    "pending",
+    "base_fee_gwei",
+];
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #222; }
+h2 { margin-top: 2rem; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.4rem 0.8rem; border-bottom: 1px solid #ddd; }
+.ok { color: #1a7f37; font-weight: bold; }
+.fail { color: #cf222e; font-weight: bold; }
+.pending { color: #888; }
+svg { vertical-align: middle; }
+";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// The headline value of a result: the first known numeric detail field, or the
+/// latency when the check has none.
+fn primary_value(result: &CheckResult) -> (String, f64) {
+    for key in VALUE_KEYS {
+        let number = match result.detail.get(key) {
+            Some(serde_json::Value::Number(n)) => n.as_f64(),
+            Some(serde_json::Value::String(s)) => s.parse::<f64>().ok(),
+            _ => None,
+        };
+        if let Some(n) = number {
+            return (format!("{} {}", key, n), n);
+        }
+    }
+    (
+        format!("latency {} ms", result.latency_ms),
+        result.latency_ms as f64,
+    )
+}
+
+fn format_duration(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3_600 => format!("{}m {}s", s / 60, s % 60),
+        s if s < 86_400 => format!("{}h {}m", s / 3_600, (s % 3_600) / 60),
+        s => format!("{}d {}h", s / 86_400, (s % 86_400) / 3_600),
+    }
+}
+
+/// Inline SVG polyline of the recent primary values; unhealthy points are red.
+fn sparkline(entry: &CheckEntry) -> String {
+    const WIDTH: f64 = 120.0;
+    const HEIGHT: f64 = 24.0;
+    let values: Vec<(f64, bool)> = entry
+        .recent
+        .iter()
+        .map(|r| (primary_value(r).1, r.healthy))
+        .collect();
+    if values.len() < 2 {
+        return String::new();
+    }
+    let min = values.iter().map(|v| v.0).fold(f64::INFINITY, f64::min);
+    let max = values.iter().map(|v| v.0).fold(f64::NEG_INFINITY, f64::max);
+    let range = if max > min { max - min } else { 1.0 };
+    let step = WIDTH / (values.len() - 1) as f64;
+    let point = |i: usize, v: f64| {
+        (
+            i as f64 * step,
+            HEIGHT - 2.0 - (v - min) / range * (HEIGHT - 4.0),
+        )
+    };
+
+    let mut svg = format!(
+        r##"<svg width="{}" height="{}" viewBox="0 0 {} {}"><polyline fill="none" stroke="#888" stroke-width="1.5" points=""##,
+        WIDTH, HEIGHT, WIDTH, HEIGHT
+    );
+    for (i, (v, _)) in values.iter().enumerate() {
+        let (x, y) = point(i, *v);
+        let _ = write!(svg, "{:.1},{:.1} ", x, y);
+    }
+    svg.push_str(r#""/>"#);
+    for (i, (v, healthy)) in values.iter().enumerate() {
+        if !healthy {
+            let (x, y) = point(i, *v);
+            let _ = write!(
+                svg,
+                r##"<circle cx="{:.1}" cy="{:.1}" r="2" fill="#cf222e"/>"##,
+                x, y
+            );
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Render the status page for every configured target and check.
+pub fn render(config: &Config, state: &MonitorState, now: u64) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+         <meta http-equiv=\"refresh\" content=\"{}\">\
+         <title>Monitor status</title><style>{}</style></head><body>\
+         <h1>Monitor status</h1>",
+        config.poll_interval_secs.max(5),
+        STYLE
+    );
+
+    if config.targets.is_empty() {
+        html.push_str("<p>No targets configured (set <code>MONITOR_CONFIG</code>).</p>");
+    }
+
+    for target in &config.targets {
+        let _ = write!(
+            html,
+            "<h2>{}</h2><table><tr><th>Check</th><th>State</th><th>Last value</th>\
+             <th>Since last change</th><th>Recent</th><th>Checked</th></tr>",
+            escape(&target.name)
+        );
+        for check in target.check_names() {
+            match state.entry(&target.name, &check) {
+                Some(entry) => {
+                    let latest = &entry.latest;
+                    let class = if latest.healthy { "ok" } else { "fail" };
+                    let _ = write!(
+                        html,
+                        "<tr><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td>\
+                         <td>{}</td><td>{} ago</td></tr>",
+                        escape(&check),
+                        class,
+                        escape(&latest.status),
+                        escape(&primary_value(latest).0),
+                        format_duration(now.saturating_sub(entry.changed_at)),
+                        sparkline(&entry),
+                        format_duration(now.saturating_sub(latest.checked_at)),
+                    );
+                }
+                None => {
+                    let _ = write!(
+                        html,
+                        "<tr><td>{}</td><td class=\"pending\">pending</td>\
+                         <td></td><td></td><td></td><td></td></tr>",
+                        escape(&check)
+                    );
+                }
+            }
+        }
+        html.push_str("</table>");
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+/// HTTP handler for `/status`: a self-contained HTML page that refreshes itself.
+pub async fn status_page(
+    config: Arc<Config>,
+    state: Arc<MonitorState>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    Ok(warp::reply::html(render(&config, &state, now)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    fn result(check: &str, healthy: bool, checked_at: u64, difference: i64) -> CheckResult {
+        CheckResult {
+            target: "l1".to_string(),
+            check: check.to_string(),
+            healthy,
+            status: if healthy { "ok" } else { "<lagging>" }.to_string(),
+            detail: serde_json::json!({ "difference": difference }),
+            checked_at,
+            latency_ms: 12,
+        }
+    }
+
+    #[test]
+    fn test_primary_value_prefers_known_fields() {
+        let r = result("finalized_latest_diff", true, 0, 64);
+        assert_eq!(primary_value(&r), ("difference 64".to_string(), 64.0));
+
+        let mut r = result("sync", true, 0, 0);
+        r.detail = serde_json::json!({ "balance_decimal": "1000" });
+        assert_eq!(primary_value(&r).1, 1000.0);
+        r.detail = serde_json::Value::Null;
+        assert_eq!(primary_value(&r).0, "latency 12 ms");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(5), "5s");
+        assert_eq!(format_duration(125), "2m 5s");
+        assert_eq!(format_duration(7_260), "2h 1m");
+        assert_eq!(format_duration(90_000), "1d 1h");
+    }
+
+    #[test]
+    fn test_render_lists_configured_checks() {
+        let config = parse_config(
+            r#"{"targets":[{"name":"l1","rpc":"http://x","checks":[
+                {"kind":"finalized_latest_diff","diff":64},{"kind":"sync"}]}]}"#,
+        )
+        .unwrap();
+        let state = MonitorState::default();
+        state.record(result("finalized_latest_diff", true, 100, 10));
+        state.record(result("finalized_latest_diff", false, 130, 80));
+
+        let html = render(&config, &state, 190);
+        assert!(html.contains("http-equiv=\"refresh\""));
+        assert!(!html.contains("<script"));
+        assert!(html.contains("&lt;lagging&gt;"));
+        assert!(html.contains("difference 80"));
+        assert!(html.contains("<td>1m 0s</td>"));
+        assert!(html.contains("<polyline"));
+        // Not yet polled.
+        assert!(html.contains("<td>sync</td><td class=\"pending\">pending</td>"));
+    }
+}
@@ -0,0 +1,433 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::{parse_hex_u128, rpc_batch, BatchRejections};
+
+const WEI_PER_GWEI: f64 = 1_000_000_000.0;
+const DEFAULT_FEE_HISTORY_BLOCKS: u64 = 5;
+const REWARD_PERCENTILES: [u32; 3] = [25, 50, 75];
+
+#[derive(Serialize)]
+struct PriorityFeePercentiles {
+    p25_gwei: f64,
+    p50_gwei: f64,
+    p75_gwei: f64,
+}
+
+#[derive(Serialize)]
+struct GasResponse {
+    gas_price_gwei: f64,
+    base_fee_gwei: f64,
+    max_priority_fee_gwei: f64,
+    priority_fee_percentiles: Option<PriorityFeePercentiles>,
+    // `None` before Cancun or on chains without blobs.
+    blob_base_fee_gwei: Option<f64>,
+    alerts: Vec<String>,
+    status: String,
+}
+
+impl GasResponse {
+    fn error(status: String) -> Self {
+        GasResponse {
+            gas_price_gwei: 0.0,
+            base_fee_gwei: 0.0,
+            max_priority_fee_gwei: 0.0,
+            priority_fee_percentiles: None,
+            blob_base_fee_gwei: None,
+            alerts: vec![],
+            status,
+        }
+    }
+}
+
+/// Gwei thresholds for `/gas`; a `None` threshold is not checked.
+#[derive(Default)]
+pub struct GasThresholds {
+    pub max_gas_price: Option<f64>,
+    pub max_base_fee: Option<f64>,
+    pub max_priority_fee: Option<f64>,
+    pub max_blob_fee: Option<f64>,
+}
+
+/// Decoded `eth_feeHistory` result: the next block's base fees plus the newest
+/// block's reward percentiles.
+struct FeeHistory {
+    next_base_fee: u128,
+    next_blob_base_fee: Option<u128>,
+    latest_rewards: Option<Vec<u128>>,
+}
+
+fn wei_to_gwei(wei: u128) -> f64 {
+    wei as f64 / WEI_PER_GWEI
+}
+
+fn hex_value_to_u128(value: &Value) -> Result<u128, Box<dyn std::error::Error>> {
+    let s = value.as_str().ok_or("expected a hex string quantity")?;
+    parse_hex_u128(s)
+}
+
+/// The last entry of a fee-history array is the fee for the block after `newest`,
+/// which is the value a transaction submitted now would pay.
+fn last_quantity(result: &Value, field: &str) -> Result<Option<u128>, Box<dyn std::error::Error>> {
+    match result.get(field).and_then(|v| v.as_array()) {
+        Some(values) => match values.last() {
+            Some(v) => Ok(Some(hex_value_to_u128(v)?)),
+            None => Ok(None),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Everything `/gas` reads from the node, fetched in one batch request.
+struct Fees {
+    gas_price: u128,
+    max_priority_fee: u128,
+    history: FeeHistory,
+}
+
+async fn get_fees(
+    rpc_url: Option<String>,
+    block_count: u64,
+    rejections: &BatchRejections,
+) -> Result<Fees, Box<dyn std::error::Error>> {
+    let percentiles = REWARD_PERCENTILES
+        .iter()
+        .map(|p| Value::from(*p))
+        .collect::<Vec<_>>();
+    let results = rpc_batch(
+        rpc_url,
+        vec![
+            ("eth_gasPrice", vec![]),
+            ("eth_maxPriorityFeePerGas", vec![]),
+            (
+                "eth_feeHistory",
+                vec![
+                    Value::String(format!("0x{:x}", block_count)),
+                    Value::String("latest".to_string()),
+                    Value::Array(percentiles),
+                ],
+            ),
+        ],
+        rejections,
+    )
+    .await?;
+
+    Ok(Fees {
+        gas_price: hex_value_to_u128(&results[0])?,
+        max_priority_fee: hex_value_to_u128(&results[1])?,
+        history: parse_fee_history(&results[2])?,
+    })
+}
+
+fn parse_fee_history(result: &Value) -> Result<FeeHistory, Box<dyn std::error::Error>> {
+    let next_base_fee = last_quantity(result, "baseFeePerGas")?
+        .ok_or("eth_feeHistory returned no baseFeePerGas")?;
+    let next_blob_base_fee = last_quantity(result, "baseFeePerBlobGas")?;
+
+    let latest_rewards = match result
+        .get("reward")
+        .and_then(|r| r.as_array())
+        .and_then(|rows| rows.last())
+        .and_then(|row| row.as_array())
+    {
+        Some(row) => Some(
+            row.iter()
+                .map(hex_value_to_u128)
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        None => None,
+    };
+
+    Ok(FeeHistory {
+        next_base_fee,
+        next_blob_base_fee,
+        latest_rewards,
+    })
+}
+
+fn check_threshold(alerts: &mut Vec<String>, name: &str, value_gwei: f64, max_gwei: Option<f64>) {
+    if let Some(max) = max_gwei {
+        if value_gwei > max {
+            alerts.push(format!(
+                "{} {:.3} gwei above {} gwei",
+                name, value_gwei, max
+            ));
+        }
+    }
+}
+
+/// HTTP handler for `/gas`. Reports gas price, next-block base fee, priority-fee
+/// percentiles and blob base fee; returns 503 when any configured gwei threshold
+/// is exceeded and 500 when the node cannot be queried.
+pub async fn check_gas(
+    rpc_url: Option<String>,
+    blocks: Option<u64>,
+    thresholds: GasThresholds,
+    batch_rejections: Arc<BatchRejections>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let block_count = blocks
+        .filter(|b| *b > 0)
+        .unwrap_or(DEFAULT_FEE_HISTORY_BLOCKS);
+
+    let Fees {
+        gas_price,
+        max_priority_fee,
+        history,
+    } = match get_fees(rpc_url, block_count, &batch_rejections).await {
+        Ok(f) => f,
+        Err(e) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&GasResponse::error(format!("error: {}", e))),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+
+    let percentiles = history
+        .latest_rewards
+        .as_ref()
+        .and_then(|r| match r.as_slice() {
+            [p25, p50, p75] => Some(PriorityFeePercentiles {
+                p25_gwei: wei_to_gwei(*p25),
+                p50_gwei: wei_to_gwei(*p50),
+                p75_gwei: wei_to_gwei(*p75),
+            }),
+            _ => None,
+        });
+
+    let gas_price_gwei = wei_to_gwei(gas_price);
+    let base_fee_gwei = wei_to_gwei(history.next_base_fee);
+    let max_priority_fee_gwei = wei_to_gwei(max_priority_fee);
+    let blob_base_fee_gwei = history.next_blob_base_fee.map(wei_to_gwei);
+
+    let mut alerts = Vec::new();
+    check_threshold(
+        &mut alerts,
+        "gas price",
+        gas_price_gwei,
+        thresholds.max_gas_price,
+    );
+    check_threshold(
+        &mut alerts,
+        "base fee",
+        base_fee_gwei,
+        thresholds.max_base_fee,
+    );
+    check_threshold(
+        &mut alerts,
+        "priority fee",
+        max_priority_fee_gwei,
+        thresholds.max_priority_fee,
+    );
+    if let Some(blob_fee) = blob_base_fee_gwei {
+        check_threshold(
+            &mut alerts,
+            "blob base fee",
+            blob_fee,
+            thresholds.max_blob_fee,
+        );
+    }
+
+    let healthy = alerts.is_empty();
+    let response = GasResponse {
+        gas_price_gwei,
+        base_fee_gwei,
+        max_priority_fee_gwei,
+        priority_fee_percentiles: percentiles,
+        blob_base_fee_gwei,
+        alerts,
+        status: if healthy {
+            "gas_normal".to_string()
+        } else {
+            "gas_high".to_string()
+        },
+    };
+
+    if healthy {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::OK,
+        ))
+    } else {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{batch_reply, echo_rpc_id, rpc_node};
+    use mockito::Server;
+    use warp::Reply;
+
+    // 30 gwei base fee for the next block, 2 gwei blob base fee.
+    const FEE_HISTORY_BODY: &str = r#"{"jsonrpc":"2.0","id":1,"result":{
+        "oldestBlock":"0x10",
+        "baseFeePerGas":["0x5d21dba00","0x6fc23ac00"],
+        "gasUsedRatio":[0.5],
+        "reward":[["0x3b9aca00","0x77359400","0xb2d05e00"]],
+        "baseFeePerBlobGas":["0x1","0x77359400"],
+        "blobGasUsedRatio":[0.33]}}"#;
+
+    async fn mock_gas_node(server: &mut mockito::ServerGuard, fee_history_body: &str) {
+        let fee_history_body = fee_history_body.to_string();
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(rpc_node(move |req| match req["method"].as_str().unwrap() {
+                "eth_gasPrice" => r#"{"jsonrpc":"2.0","id":1,"result":"0x77359400"}"#.to_string(),
+                "eth_maxPriorityFeePerGas" => {
+                    r#"{"jsonrpc":"2.0","id":1,"result":"0x3b9aca00"}"#.to_string()
+                }
+                _ => fee_history_body.clone(),
+            }))
+            .create_async()
+            .await;
+    }
+
+    async fn gas_status(
+        server: &mockito::ServerGuard,
+        thresholds: GasThresholds,
+    ) -> warp::http::StatusCode {
+        check_gas(Some(server.url()), None, thresholds, Default::default())
+            .await
+            .unwrap()
+            .into_response()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_get_fee_history_uses_next_block_fees() {
+        let mut server = Server::new_async().await;
+        mock_gas_node(&mut server, FEE_HISTORY_BODY).await;
+
+        let history = get_fees(Some(server.url()), 1, &Default::default())
+            .await
+            .unwrap()
+            .history;
+        assert_eq!(history.next_base_fee, 30_000_000_000);
+        assert_eq!(history.next_blob_base_fee, Some(2_000_000_000));
+        assert_eq!(
+            history.latest_rewards,
+            Some(vec![1_000_000_000, 2_000_000_000, 3_000_000_000])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_fee_history_without_blob_fields() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"result":{"oldestBlock":"0x10","baseFeePerGas":["0x1","0x2"],"gasUsedRatio":[0.5]}}"#;
+        let mut server = Server::new_async().await;
+        mock_gas_node(&mut server, body).await;
+
+        let history = get_fees(Some(server.url()), 1, &Default::default())
+            .await
+            .unwrap()
+            .history;
+        assert_eq!(history.next_base_fee, 2);
+        assert_eq!(history.next_blob_base_fee, None);
+        assert!(history.latest_rewards.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_fees_uses_a_single_batch_request() {
+        let mut server = Server::new_async().await;
+        let fee_history: Value =
+            serde_json::from_str::<Value>(FEE_HISTORY_BODY).unwrap()["result"].clone();
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(batch_reply(vec![
+                Value::from("0x77359400"),
+                Value::from("0x3b9aca00"),
+                fee_history,
+            ]))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let fees = get_fees(Some(server.url()), 1, &Default::default())
+            .await
+            .unwrap();
+        mock.assert_async().await;
+        assert_eq!(fees.gas_price, 2_000_000_000);
+        assert_eq!(fees.max_priority_fee, 1_000_000_000);
+        assert_eq!(fees.history.next_base_fee, 30_000_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_check_gas_below_thresholds_returns_ok() {
+        let mut server = Server::new_async().await;
+        mock_gas_node(&mut server, FEE_HISTORY_BODY).await;
+
+        let thresholds = GasThresholds {
+            max_gas_price: Some(50.0),
+            max_base_fee: Some(50.0),
+            max_priority_fee: Some(5.0),
+            max_blob_fee: Some(10.0),
+        };
+        assert_eq!(
+            gas_status(&server, thresholds).await,
+            warp::http::StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_gas_base_fee_spike_returns_unavailable() {
+        let mut server = Server::new_async().await;
+        mock_gas_node(&mut server, FEE_HISTORY_BODY).await;
+
+        let thresholds = GasThresholds {
+            max_base_fee: Some(20.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            gas_status(&server, thresholds).await,
+            warp::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_gas_blob_fee_spike_returns_unavailable() {
+        let mut server = Server::new_async().await;
+        mock_gas_node(&mut server, FEE_HISTORY_BODY).await;
+
+        let thresholds = GasThresholds {
+            max_blob_fee: Some(1.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            gas_status(&server, thresholds).await,
+            warp::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_gas_rpc_error_returns_error() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(
+                r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"method not found"}}"#,
+            ))
+            .create_async()
+            .await;
+
+        assert_eq!(
+            gas_status(&server, GasThresholds::default()).await,
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_wei_to_gwei() {
+        assert_eq!(wei_to_gwei(1_500_000_000), 1.5);
+    }
+}
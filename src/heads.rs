@@ -0,0 +1,442 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::{sleep, timeout, Duration};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use crate::config::{CheckConfig, Target};
+use crate::state::CheckResult;
+use crate::{get_block_header_by_tag, next_rpc_id, strip_hex_prefix};
+
+/// Reconnect backoff, doubling from the minimum up to the maximum.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// How often the head is polled over HTTP while the socket is down.
+const HTTP_FALLBACK_INTERVAL: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadSource {
+    WebSocket,
+    Http,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Head {
+    pub number: u64,
+    pub hash: Option<String>,
+    /// Unix milliseconds when the monitor first saw this head.
+    pub arrived_at_ms: u64,
+    pub source: HeadSource,
+}
+
+/// A target's head subscription as seen by the rest of the monitor.
+#[derive(Serialize, Clone, Default)]
+pub struct Subscription {
+    pub head: Option<Head>,
+    pub connected: bool,
+    /// Times the socket has dropped since startup.
+    pub disconnects: u64,
+}
+
+/// Latest heads of the targets with a `ws` endpoint, shared with the poller and
+/// the HTTP handlers.
+#[derive(Default)]
+pub struct HeadTracker {
+    targets: RwLock<HashMap<String, Subscription>>,
+}
+
+impl HeadTracker {
+    pub fn get(&self, target: &str) -> Option<Subscription> {
+        self.targets.read().unwrap().get(target).cloned()
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, Subscription> {
+        self.targets.read().unwrap().clone()
+    }
+
+    fn set_connected(&self, target: &str, connected: bool) {
+        let mut targets = self.targets.write().unwrap();
+        let subscription = targets.entry(target.to_string()).or_default();
+        if subscription.connected && !connected {
+            subscription.disconnects += 1;
+        }
+        subscription.connected = connected;
+    }
+
+    /// Record `head` if it is newer than the current one, or replaces it at the same
+    /// height (a reorg). Repeats are ignored so the arrival time only moves when the
+    /// chain does.
+    pub fn observe(&self, target: &str, head: Head) -> bool {
+        let mut targets = self.targets.write().unwrap();
+        let subscription = targets.entry(target.to_string()).or_default();
+        let newer = match &subscription.head {
+            None => true,
+            Some(current) => {
+                head.number > current.number
+                    || (head.number == current.number
+                        && head.hash.is_some()
+                        && head.hash != current.hash)
+            }
+        };
+        if newer {
+            subscription.head = Some(head);
+        }
+        newer
+    }
+}
+
+fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// The head number and hash carried by an `eth_subscription` notification, or
+/// `None` when `message` is anything else.
+fn parse_new_head(message: &str) -> Option<(u64, Option<String>)> {
+    let value: serde_json::Value = serde_json::from_str(message).ok()?;
+    if value.get("method")?.as_str()? != "eth_subscription" {
+        return None;
+    }
+    let header = value.get("params")?.get("result")?;
+    let number = u64::from_str_radix(strip_hex_prefix(header.get("number")?.as_str()?), 16).ok()?;
+    let hash = header
+        .get("hash")
+        .and_then(|h| h.as_str())
+        .map(|h| h.to_string());
+    Some((number, hash))
+}
+
+/// Connect, subscribe to `newHeads` and record heads until the socket fails or
+/// stays silent for `stall`. Returns why the subscription ended.
+async fn subscribe(target: &str, url: &str, stall: Duration, tracker: &HeadTracker) -> String {
+    let mut socket = match timeout(CONNECT_TIMEOUT, connect_async(url)).await {
+        Ok(Ok((socket, _))) => socket,
+        Ok(Err(e)) => return format!("connect failed: {}", e),
+        Err(_) => return "connect timed out".to_string(),
+    };
+
+    let id = next_rpc_id();
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "eth_subscribe",
+        "params": ["newHeads"],
+    });
+    if let Err(e) = socket.send(Message::Text(request.to_string())).await {
+        return format!("subscribe failed: {}", e);
+    }
+    tracker.set_connected(target, true);
+    tracing::info!(target, "head subscription connected");
+
+    loop {
+        let text = match timeout(stall, socket.next()).await {
+            Err(_) => return format!("no message for {}s", stall.as_secs()),
+            Ok(None) => return "connection closed".to_string(),
+            Ok(Some(Err(e))) => return e.to_string(),
+            Ok(Some(Ok(Message::Text(text)))) => text,
+            Ok(Some(Ok(Message::Close(_)))) => return "closed by server".to_string(),
+            // Pings are answered by tungstenite itself.
+            Ok(Some(Ok(_))) => continue,
+        };
+
+        if let Some((number, hash)) = parse_new_head(&text) {
+            let head = Head {
+                number,
+                hash,
+                arrived_at_ms: unix_now_ms(),
+                source: HeadSource::WebSocket,
+            };
+            if tracker.observe(target, head) {
+                tracing::debug!(target, block_number = number, "new head");
+            }
+            continue;
+        }
+
+        let reply: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
+        if reply.get("id").and_then(|i| i.as_u64()) == Some(id) {
+            if let Some(error) = reply.get("error").filter(|e| !e.is_null()) {
+                return format!("eth_subscribe failed: {}", error);
+            }
+        }
+    }
+}
+
+/// Fetch the latest head over HTTP, for seeding and while the socket is down.
+async fn poll_http(target: &Target, tracker: &HeadTracker) {
+    let error = match get_block_header_by_tag(Some(target.rpc.clone()), "latest").await {
+        Ok(header) => {
+            tracker.observe(
+                &target.name,
+                Head {
+                    number: header.number.max(0) as u64,
+                    hash: header.hash,
+                    arrived_at_ms: unix_now_ms(),
+                    source: HeadSource::Http,
+                },
+            );
+            return;
+        }
+        Err(e) => e.to_string(),
+    };
+    tracing::warn!(target = %target.name, error = %error, "http head poll failed");
+}
+
+/// Keep `target`'s head current from its `ws` endpoint for as long as the process
+/// runs. Dropped sockets are retried with exponential backoff, and the head is
+/// polled over HTTP until the subscription is back.
+pub async fn run(target: Target, tracker: Arc<HeadTracker>) {
+    let url = match &target.ws {
+        Some(url) => url.clone(),
+        None => return,
+    };
+    let stall = Duration::from_secs(target.head_stall_secs.max(1));
+    let mut backoff = MIN_BACKOFF;
+
+    loop {
+        // Seed the head before subscribing; notifications only come with new blocks.
+        poll_http(&target, &tracker).await;
+
+        let connected_at = Instant::now();
+        let reason = subscribe(&target.name, &url, stall, &tracker).await;
+        tracker.set_connected(&target.name, false);
+        // A subscription that held for a while starts the backoff over.
+        if connected_at.elapsed() > MAX_BACKOFF {
+            backoff = MIN_BACKOFF;
+        }
+        tracing::warn!(
+            target = %target.name,
+            reason = %reason,
+            retry_in_secs = backoff.as_secs(),
+            "head subscription dropped"
+        );
+
+        let retry_at = Instant::now() + backoff;
+        loop {
+            let remaining = retry_at.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            sleep(remaining.min(HTTP_FALLBACK_INTERVAL)).await;
+            poll_http(&target, &tracker).await;
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// The `sync` check of a subscribed target: healthy while the newest head, from
+/// the socket or the HTTP fallback, is at most `head_stall_secs` old.
+pub fn sync_result(target: &Target, tracker: &HeadTracker, now_ms: u64) -> CheckResult {
+    let subscription = tracker.get(&target.name).unwrap_or_default();
+    let checked_at = now_ms / 1000;
+
+    let head = match subscription.head {
+        Some(head) => head,
+        None => {
+            return CheckResult {
+                target: target.name.clone(),
+                check: CheckConfig::Sync.name(),
+                healthy: false,
+                status: "no_head".to_string(),
+                detail: serde_json::json!({ "connected": subscription.connected }),
+                checked_at,
+                latency_ms: 0,
+            }
+        }
+    };
+
+    let seconds_since_head = now_ms.saturating_sub(head.arrived_at_ms) / 1000;
+    let healthy = seconds_since_head <= target.head_stall_secs;
+    CheckResult {
+        target: target.name.clone(),
+        check: CheckConfig::Sync.name(),
+        healthy,
+        status: if healthy { "synced" } else { "stalled" }.to_string(),
+        detail: serde_json::json!({
+            "block_number_hex": format!("0x{:x}", head.number),
+            "block_number_decimal": head.number,
+            "hash": head.hash,
+            "seconds_since_head": seconds_since_head,
+            "source": head.source,
+            "connected": subscription.connected,
+            "disconnects": subscription.disconnects,
+        }),
+        checked_at,
+        latency_ms: 0,
+    }
+}
+
+#[derive(Serialize)]
+struct HeadsResponse {
+    targets: HashMap<String, Subscription>,
+    status: String,
+}
+
+/// HTTP handler for `/heads`: the tracked head of every target with a `ws` endpoint.
+pub async fn get_heads(tracker: Arc<HeadTracker>) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&HeadsResponse {
+        targets: tracker.snapshot(),
+        status: "ok".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+    use crate::tests::echo_rpc_id;
+    use mockito::Server;
+    use tokio::net::TcpListener;
+
+    fn target(rpc: &str, ws: &str) -> Target {
+        let config = parse_config(&format!(
+            r#"{{"targets":[{{"name":"l1","rpc":"{}","ws":"{}","head_stall_secs":30,
+                "checks":[{{"kind":"sync"}}]}}]}}"#,
+            rpc, ws
+        ))
+        .unwrap();
+        config.targets[0].clone()
+    }
+
+    async fn mock_latest(server: &mut mockito::ServerGuard, number: u64) {
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":{{"number":"0x{:x}","hash":"0xaa"}}}}"#,
+                number
+            )))
+            .create_async()
+            .await;
+    }
+
+    /// Wait until the tracked head satisfies `done`, for up to five seconds.
+    async fn wait_for(tracker: &HeadTracker, done: impl Fn(&Head) -> bool) -> Head {
+        for _ in 0..100 {
+            if let Some(head) = tracker.get("l1").and_then(|s| s.head) {
+                if done(&head) {
+                    return head;
+                }
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        panic!("head not observed in time");
+    }
+
+    #[test]
+    fn test_parse_new_head() {
+        let notification = r#"{"jsonrpc":"2.0","method":"eth_subscription","params":{
+            "subscription":"0x9ce5","result":{"number":"0x1b4","hash":"0xdc0818"}}}"#;
+        assert_eq!(
+            parse_new_head(notification),
+            Some((436, Some("0xdc0818".to_string())))
+        );
+        assert_eq!(
+            parse_new_head(r#"{"jsonrpc":"2.0","id":1,"result":"0x9ce5"}"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_observe_only_moves_on_new_heads() {
+        let tracker = HeadTracker::default();
+        let head = |number, hash: &str, at| Head {
+            number,
+            hash: Some(hash.to_string()),
+            arrived_at_ms: at,
+            source: HeadSource::WebSocket,
+        };
+        assert!(tracker.observe("l1", head(10, "0xa", 1)));
+        assert!(!tracker.observe("l1", head(10, "0xa", 2)));
+        assert!(!tracker.observe("l1", head(9, "0xb", 3)));
+        // Same height, different block: a reorg.
+        assert!(tracker.observe("l1", head(10, "0xc", 4)));
+        assert_eq!(tracker.get("l1").unwrap().head.unwrap().arrived_at_ms, 4);
+    }
+
+    #[test]
+    fn test_sync_result_fails_after_stall() {
+        let target = target("http://x", "ws://x");
+        let tracker = HeadTracker::default();
+        assert_eq!(sync_result(&target, &tracker, 0).status, "no_head");
+
+        tracker.observe(
+            "l1",
+            Head {
+                number: 5,
+                hash: None,
+                arrived_at_ms: 100_000,
+                source: HeadSource::Http,
+            },
+        );
+        assert!(sync_result(&target, &tracker, 130_000).healthy);
+        let stalled = sync_result(&target, &tracker, 131_000);
+        assert_eq!(stalled.status, "stalled");
+        assert_eq!(stalled.detail["seconds_since_head"], 31);
+    }
+
+    #[tokio::test]
+    async fn test_run_records_heads_from_subscription() {
+        let mut rpc = Server::new_async().await;
+        mock_latest(&mut rpc, 0x10).await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let request = socket.next().await.unwrap().unwrap().into_text().unwrap();
+            let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+            assert_eq!(request["method"], "eth_subscribe");
+            let reply = serde_json::json!({"jsonrpc":"2.0","id":request["id"],"result":"0x9ce5"});
+            socket.send(Message::Text(reply.to_string())).await.unwrap();
+            let notification = r#"{"jsonrpc":"2.0","method":"eth_subscription","params":{
+                "subscription":"0x9ce5","result":{"number":"0x20","hash":"0xbb"}}}"#;
+            socket
+                .send(Message::Text(notification.to_string()))
+                .await
+                .unwrap();
+            // Hold the connection open.
+            let _ = socket.next().await;
+        });
+
+        let tracker = Arc::new(HeadTracker::default());
+        let task = tokio::spawn(run(target(&rpc.url(), &ws_url), tracker.clone()));
+
+        let head = wait_for(&tracker, |h| h.number == 0x20).await;
+        assert_eq!(head.source, HeadSource::WebSocket);
+        assert_eq!(head.hash.as_deref(), Some("0xbb"));
+        assert!(tracker.get("l1").unwrap().connected);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_run_falls_back_to_http_when_socket_is_down() {
+        let mut rpc = Server::new_async().await;
+        mock_latest(&mut rpc, 0x10).await;
+        // Nothing listens on the port once the listener is dropped.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let tracker = Arc::new(HeadTracker::default());
+        let task = tokio::spawn(run(
+            target(&rpc.url(), &format!("ws://127.0.0.1:{}", port)),
+            tracker.clone(),
+        ));
+
+        let head = wait_for(&tracker, |h| h.number == 0x10).await;
+        assert_eq!(head.source, HeadSource::Http);
+        assert!(!tracker.get("l1").unwrap().connected);
+        task.abort();
+    }
+}
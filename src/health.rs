@@ -0,0 +1,306 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{Config, Severity, Target};
+use crate::state::{CheckResult, MonitorState};
+
+/// Poll rounds a result may be missed by before it counts as stale.
+const STALE_AFTER_ROUNDS: u64 = 3;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+enum Level {
+    Pending,
+    Ok,
+    Warning,
+    Critical,
+}
+
+#[derive(Serialize)]
+struct CheckHealth {
+    target: String,
+    chain: Option<String>,
+    check: String,
+    severity: Severity,
+    level: Level,
+    status: String,
+    checked_at: Option<u64>,
+    /// The poller has not refreshed this result for several rounds.
+    stale: bool,
+}
+
+#[derive(Serialize, Default)]
+struct Summary {
+    ok: usize,
+    warning: usize,
+    critical: usize,
+    pending: usize,
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    status: String,
+    targets: usize,
+    summary: Summary,
+    checks: Vec<CheckHealth>,
+}
+
+/// `?chain=` must match exactly; `?tags=a,b` matches targets with any listed tag.
+fn matches(target: &Target, chain: Option<&String>, tags: &[String]) -> bool {
+    let chain_ok = chain
+        .map(|c| target.chain.as_deref() == Some(c.as_str()))
+        .unwrap_or(true);
+    let tags_ok = tags.is_empty() || tags.iter().any(|t| target.tags.contains(t));
+    chain_ok && tags_ok
+}
+
+/// Age in seconds past which a result is stale: `STALE_AFTER_ROUNDS` poll intervals,
+/// plus one RPC timeout since a round runs as long as its slowest check.
+fn stale_after(config: &Config) -> u64 {
+    STALE_AFTER_ROUNDS * config.poll_interval_secs + crate::RPC_TIMEOUT.as_secs()
+}
+
+/// A failing check counts at its configured severity. A passing check whose smoothed
+/// `state` (see `hysteresis`) is `warning` counts as a warning. A stale result is
+/// critical whatever it said: the poller is stuck or the check hangs.
+fn level(result: Option<&CheckResult>, severity: Severity, stale: bool) -> Level {
+    match result {
+        None => Level::Pending,
+        Some(_) if stale => Level::Critical,
+        Some(r) if !r.healthy => match severity {
+            Severity::Critical => Level::Critical,
+            Severity::Warning => Level::Warning,
+        },
+        Some(r) if r.detail.get("state").and_then(|s| s.as_str()) == Some("warning") => {
+            Level::Warning
+        }
+        Some(_) => Level::Ok,
+    }
+}
+
+/// HTTP handler for `/health?chain=&tags=`: every configured check's latest poller
+/// result. 503 when any check is critical or stale; warnings and not-yet-polled checks
+/// are reported but keep the 200. 404 when the filters match no target.
+pub async fn check_health(
+    config: Arc<Config>,
+    state: Arc<MonitorState>,
+    params: HashMap<String, String>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    Ok(health_at(&config, &state, &params, now))
+}
+
+fn health_at(
+    config: &Config,
+    state: &MonitorState,
+    params: &HashMap<String, String>,
+    now: u64,
+) -> warp::reply::WithStatus<warp::reply::Json> {
+    let chain = params.get("chain").filter(|c| !c.is_empty());
+    let tags: Vec<String> = params
+        .get("tags")
+        .map(|t| {
+            t.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let targets: Vec<&Target> = config
+        .targets
+        .iter()
+        .filter(|t| matches(t, chain, &tags))
+        .collect();
+
+    let mut response = HealthResponse {
+        status: "".to_string(),
+        targets: targets.len(),
+        summary: Summary::default(),
+        checks: Vec::new(),
+    };
+
+    if targets.is_empty() {
+        response.status = "error: no configured target matches the filters".to_string();
+        return warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::NOT_FOUND,
+        );
+    }
+
+    for target in targets {
+        for (check, severity) in target.check_severities() {
+            let result = state.get(&target.name, &check);
+            let stale = result
+                .as_ref()
+                .is_some_and(|r| now.saturating_sub(r.checked_at) > stale_after(config));
+            let level = level(result.as_ref(), severity, stale);
+            match level {
+                Level::Pending => response.summary.pending += 1,
+                Level::Ok => response.summary.ok += 1,
+                Level::Warning => response.summary.warning += 1,
+                Level::Critical => response.summary.critical += 1,
+            }
+            response.checks.push(CheckHealth {
+                target: target.name.clone(),
+                chain: target.chain.clone(),
+                check,
+                severity,
+                level,
+                status: result
+                    .as_ref()
+                    .map(|r| r.status.clone())
+                    .unwrap_or_else(|| "pending".to_string()),
+                checked_at: result.map(|r| r.checked_at),
+                stale,
+            });
+        }
+    }
+
+    let worst = response
+        .checks
+        .iter()
+        .map(|c| c.level)
+        .max()
+        .unwrap_or(Level::Ok);
+    if worst == Level::Critical {
+        response.status = "unhealthy".to_string();
+        warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::SERVICE_UNAVAILABLE,
+        )
+    } else {
+        response.status = if worst == Level::Warning {
+            "degraded".to_string()
+        } else {
+            "healthy".to_string()
+        };
+        warp::reply::with_status(warp::reply::json(&response), warp::http::StatusCode::OK)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+    use warp::Reply;
+
+    const CONFIG: &str = r#"{"targets":[
+        {"name":"l1","rpc":"http://x","chain":"ethereum","tags":["prod"],
+         "checks":[{"kind":"sync"},{"kind":"finalized_latest_diff","diff":64,"severity":"warning"}]},
+        {"name":"op","rpc":"http://y","chain":"optimism","tags":["prod","l2"],
+         "checks":[{"kind":"sync"}]}]}"#;
+
+    fn result(target: &str, check: &str, healthy: bool) -> CheckResult {
+        CheckResult {
+            target: target.to_string(),
+            check: check.to_string(),
+            healthy,
+            status: if healthy { "ok" } else { "failing" }.to_string(),
+            detail: serde_json::Value::Null,
+            checked_at: 1,
+            latency_ms: 0,
+        }
+    }
+
+    async fn health(
+        state: &Arc<MonitorState>,
+        query: &[(&str, &str)],
+    ) -> (warp::http::StatusCode, serde_json::Value) {
+        // Results are recorded at `checked_at: 1`.
+        health_after(state, query, 10).await
+    }
+
+    async fn health_after(
+        state: &Arc<MonitorState>,
+        query: &[(&str, &str)],
+        now: u64,
+    ) -> (warp::http::StatusCode, serde_json::Value) {
+        let params = query
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let config = parse_config(CONFIG).unwrap();
+        let response = health_at(&config, state, &params, now).into_response();
+        let status = response.status();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_warning_failure_is_degraded() {
+        let state = Arc::new(MonitorState::default());
+        state.record(result("l1", "sync", true));
+        state.record(result("l1", "finalized_latest_diff", false));
+        state.record(result("op", "sync", true));
+
+        let (status, json) = health(&state, &[]).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(json["status"], "degraded");
+        assert_eq!(json["summary"]["warning"], 1);
+        assert_eq!(json["summary"]["ok"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_critical_failure_is_unhealthy_and_filters_apply() {
+        let state = Arc::new(MonitorState::default());
+        state.record(result("l1", "sync", true));
+        state.record(result("op", "sync", false));
+
+        let (status, json) = health(&state, &[]).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["status"], "unhealthy");
+        // l1's finality check has not been polled yet.
+        assert_eq!(json["summary"]["pending"], 1);
+
+        let (status, json) = health(&state, &[("chain", "ethereum")]).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(json["targets"], 1);
+
+        let (status, json) = health(&state, &[("tags", "l2, staging")]).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["checks"][0]["target"], "op");
+    }
+
+    #[tokio::test]
+    async fn test_smoothed_warning_state_counts_as_warning() {
+        let state = Arc::new(MonitorState::default());
+        let mut r = result("op", "sync", true);
+        r.detail = serde_json::json!({"state": "warning"});
+        state.record(r);
+
+        let (_, json) = health(&state, &[("chain", "optimism")]).await;
+        assert_eq!(json["checks"][0]["level"], "warning");
+    }
+
+    #[tokio::test]
+    async fn test_stale_result_is_critical() {
+        let state = Arc::new(MonitorState::default());
+        state.record(result("op", "sync", true));
+        // 30s poll interval: three missed rounds plus the 30s RPC timeout.
+        let limit = 1 + 3 * 30 + 30;
+
+        let (status, json) = health_after(&state, &[("chain", "optimism")], limit).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(json["checks"][0]["stale"], false);
+
+        let (status, json) = health_after(&state, &[("chain", "optimism")], limit + 1).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["checks"][0]["level"], "critical");
+        assert_eq!(json["checks"][0]["stale"], true);
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_filter_returns_not_found() {
+        let state = Arc::new(MonitorState::default());
+        let (status, _) = health(&state, &[("chain", "base")]).await;
+        assert_eq!(status, warp::http::StatusCode::NOT_FOUND);
+    }
+}
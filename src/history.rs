@@ -0,0 +1,356 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::state::CheckResult;
+
+/// Rows returned by one `/history` request at most.
+const MAX_ROWS: u32 = 10_000;
+
+/// Check results persisted by the poller in a local SQLite file.
+pub struct History {
+    conn: Mutex<Connection>,
+}
+
+impl History {
+    pub fn open(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("failed to open history database '{}': {}", path, e))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS check_results (
+                 id INTEGER PRIMARY KEY,
+                 target TEXT NOT NULL,
+                 check_name TEXT NOT NULL,
+                 checked_at INTEGER NOT NULL,
+                 healthy INTEGER NOT NULL,
+                 status TEXT NOT NULL,
+                 latency_ms INTEGER NOT NULL,
+                 detail TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS check_results_series
+                 ON check_results (target, check_name, checked_at);
+             CREATE INDEX IF NOT EXISTS check_results_age ON check_results (checked_at);",
+        )?;
+        Ok(History {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn insert(&self, result: &CheckResult) -> Result<(), rusqlite::Error> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO check_results
+                 (target, check_name, checked_at, healthy, status, latency_ms, detail)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                result.target,
+                result.check,
+                result.checked_at as i64,
+                result.healthy,
+                result.status,
+                result.latency_ms as i64,
+                result.detail.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Delete results checked before `cutoff` (unix seconds); returns the rows removed.
+    pub fn prune(&self, cutoff: u64) -> Result<usize, rusqlite::Error> {
+        self.conn.lock().unwrap().execute(
+            "DELETE FROM check_results WHERE checked_at < ?1",
+            params![cutoff as i64],
+        )
+    }
+
+    /// The newest `limit` results between `from` and `to` inclusive, oldest first.
+    /// Empty `target` or `check` matches every value.
+    pub fn query(
+        &self,
+        target: &str,
+        check: &str,
+        from: u64,
+        to: u64,
+        limit: u32,
+    ) -> Result<Vec<CheckResult>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT target, check_name, checked_at, healthy, status, latency_ms, detail
+             FROM (SELECT * FROM check_results
+                   WHERE (?1 = '' OR target = ?1) AND (?2 = '' OR check_name = ?2)
+                     AND checked_at >= ?3 AND checked_at <= ?4
+                   ORDER BY checked_at DESC, id DESC
+                   LIMIT ?5)
+             ORDER BY checked_at, id",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                target,
+                check,
+                from as i64,
+                to.min(i64::MAX as u64) as i64,
+                limit
+            ],
+            |row| {
+                let detail: String = row.get(6)?;
+                Ok(CheckResult {
+                    target: row.get(0)?,
+                    check: row.get(1)?,
+                    checked_at: row.get::<_, i64>(2)? as u64,
+                    healthy: row.get(3)?,
+                    status: row.get(4)?,
+                    latency_ms: row.get::<_, i64>(5)? as u64,
+                    detail: serde_json::from_str(&detail).unwrap_or(serde_json::Value::Null),
+                })
+            },
+        )?;
+        rows.collect()
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(results: &[CheckResult]) -> String {
+    let mut out = String::from("checked_at,target,check,healthy,status,latency_ms,detail\n");
+    for r in results {
+        let fields = [
+            r.checked_at.to_string(),
+            csv_field(&r.target),
+            csv_field(&r.check),
+            r.healthy.to_string(),
+            csv_field(&r.status),
+            r.latency_ms.to_string(),
+            csv_field(&r.detail.to_string()),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+#[derive(Serialize)]
+struct HistoryResponse {
+    target: String,
+    check: String,
+    from: u64,
+    to: u64,
+    results: Vec<CheckResult>,
+    status: String,
+}
+
+/// HTTP handler for `/history?target=&check=&from=&to=&format=json|csv&limit=`.
+/// `from`/`to` are unix seconds (defaults: everything up to now). Returns 404 when
+/// no history database is configured and 500 when the query fails.
+pub async fn get_history(
+    history: Option<Arc<History>>,
+    params: HashMap<String, String>,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let target = params.get("target").cloned().unwrap_or_default();
+    let check = params.get("check").cloned().unwrap_or_default();
+    let from = params
+        .get("from")
+        .and_then(|f| f.parse::<u64>().ok())
+        .unwrap_or(0);
+    let to = params
+        .get("to")
+        .and_then(|t| t.parse::<u64>().ok())
+        .unwrap_or(now);
+    let limit = params
+        .get("limit")
+        .and_then(|l| l.parse::<u32>().ok())
+        .unwrap_or(MAX_ROWS)
+        .min(MAX_ROWS);
+    let csv = params.get("format").map(|f| f == "csv").unwrap_or(false);
+
+    let mut response = HistoryResponse {
+        target: target.clone(),
+        check: check.clone(),
+        from,
+        to,
+        results: Vec::new(),
+        status: "".to_string(),
+    };
+
+    let history = match history {
+        Some(h) => h,
+        None => {
+            response.status = "error: history is not configured".to_string();
+            return Ok(Box::new(warp::reply::with_status(
+                warp::reply::json(&response),
+                warp::http::StatusCode::NOT_FOUND,
+            )));
+        }
+    };
+
+    // SQLite calls block; keep them off the async worker threads.
+    let rows = tokio::task::spawn_blocking(move || {
+        history
+            .query(&target, &check, from, to, limit)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+
+    match rows {
+        Ok(results) if csv => Ok(Box::new(warp::reply::with_header(
+            to_csv(&results),
+            "content-type",
+            "text/csv",
+        ))),
+        Ok(results) => {
+            response.results = results;
+            response.status = "ok".to_string();
+            Ok(Box::new(warp::reply::json(&response)))
+        }
+        Err(e) => {
+            response.status = format!("error: {}", e);
+            Ok(Box::new(warp::reply::with_status(
+                warp::reply::json(&response),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Reply;
+
+    fn result(check: &str, checked_at: u64, status: &str) -> CheckResult {
+        CheckResult {
+            target: "l1".to_string(),
+            check: check.to_string(),
+            healthy: status == "ok",
+            status: status.to_string(),
+            detail: serde_json::json!({"difference": checked_at}),
+            checked_at,
+            latency_ms: 5,
+        }
+    }
+
+    fn history() -> Arc<History> {
+        let history = History::open(":memory:").unwrap();
+        history
+            .insert(&result("finalized_latest_diff", 100, "ok"))
+            .unwrap();
+        history
+            .insert(&result("finalized_latest_diff", 200, "lagging, badly"))
+            .unwrap();
+        history.insert(&result("sync", 150, "ok")).unwrap();
+        Arc::new(history)
+    }
+
+    async fn body(reply: Box<dyn warp::Reply>) -> (warp::http::StatusCode, String) {
+        let response = reply.into_response();
+        let status = response.status();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_query_filters_and_orders() {
+        let history = history();
+        let rows = history
+            .query("l1", "finalized_latest_diff", 0, 1_000, 100)
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].checked_at, 100);
+        assert_eq!(rows[1].detail["difference"], 200);
+
+        assert_eq!(history.query("", "", 120, 180, 100).unwrap().len(), 1);
+        assert_eq!(history.query("", "", 0, 1_000, 2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_query_limit_keeps_the_newest_rows() {
+        let history = History::open(":memory:").unwrap();
+        for checked_at in 1..=10 {
+            history.insert(&result("sync", checked_at, "ok")).unwrap();
+        }
+        let rows = history.query("l1", "sync", 0, 1_000, 3).unwrap();
+        let checked_at: Vec<u64> = rows.iter().map(|r| r.checked_at).collect();
+        assert_eq!(checked_at, vec![8, 9, 10]);
+
+        let rows = history.query("l1", "sync", 0, 5, 2).unwrap();
+        let checked_at: Vec<u64> = rows.iter().map(|r| r.checked_at).collect();
+        assert_eq!(checked_at, vec![4, 5]);
+    }
+
+    #[test]
+    fn test_prune_removes_old_rows() {
+        let history = history();
+        assert_eq!(history.prune(160).unwrap(), 2);
+        let rows = history.query("", "", 0, 1_000, 100).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].checked_at, 200);
+    }
+
+    #[tokio::test]
+    async fn test_get_history_json() {
+        let reply = get_history(
+            Some(history()),
+            params(&[("target", "l1"), ("check", "sync")]),
+        )
+        .await
+        .unwrap();
+        let (status, body) = body(reply).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["results"].as_array().unwrap().len(), 1);
+        assert_eq!(json["results"][0]["check"], "sync");
+    }
+
+    #[tokio::test]
+    async fn test_get_history_csv_quotes_fields() {
+        let reply = get_history(
+            Some(history()),
+            params(&[
+                ("check", "finalized_latest_diff"),
+                ("from", "150"),
+                ("format", "csv"),
+            ]),
+        )
+        .await
+        .unwrap();
+        let (status, body) = body(reply).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(
+            lines[0],
+            "checked_at,target,check,healthy,status,latency_ms,detail"
+        );
+        assert_eq!(
+            lines[1],
+            r#"200,l1,finalized_latest_diff,false,"lagging, badly",5,"{""difference"":200}""#
+        );
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_history_not_configured() {
+        let (status, _) = body(get_history(None, HashMap::new()).await.unwrap()).await;
+        assert_eq!(status, warp::http::StatusCode::NOT_FOUND);
+    }
+}
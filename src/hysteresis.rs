@@ -0,0 +1,320 @@
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Tracks kept before the least recently probed one is dropped; every distinct
+/// query (node, address, thresholds, damping) gets its own.
+const MAX_TRACKS: usize = 4_096;
+
+/// Severity of a thresholded check, ordered from best to worst.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    #[default]
+    Ok,
+    Warning,
+    Critical,
+}
+
+/// Which side of a threshold is bad.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    /// e.g. finality lag: bad at or above the threshold.
+    AtOrAbove,
+    /// e.g. balance: bad at or below the threshold.
+    AtOrBelow,
+}
+
+pub struct Thresholds {
+    pub warn: Option<BigInt>,
+    pub critical: BigInt,
+    pub direction: Direction,
+    /// Once a level is entered, the value must clear its threshold by this many
+    /// percent before the level is left.
+    pub margin_pct: u32,
+}
+
+impl Thresholds {
+    fn breaches(&self, value: &BigInt, threshold: &BigInt, holding: bool) -> bool {
+        let margin = if holding {
+            threshold.magnitude() * self.margin_pct / 100u32
+        } else {
+            Default::default()
+        };
+        let margin = BigInt::from(margin);
+        match self.direction {
+            Direction::AtOrAbove => *value >= threshold - margin,
+            Direction::AtOrBelow => *value <= threshold + margin,
+        }
+    }
+
+    /// Level for `value` given the level currently held; only the held level and
+    /// those worse than it are widened by the recovery margin.
+    pub fn level(&self, value: &BigInt, current: Level) -> Level {
+        if self.breaches(value, &self.critical, current >= Level::Critical) {
+            return Level::Critical;
+        }
+        match &self.warn {
+            Some(w) if self.breaches(value, w, current >= Level::Warning) => Level::Warning,
+            _ => Level::Ok,
+        }
+    }
+}
+
+/// Consecutive-result requirements and recovery margin, from query parameters
+/// (`fail_after`, `recover_after`, `margin_pct`) or check configuration.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct Damping {
+    /// Consecutive worse results needed before the smoothed level gets worse.
+    pub fail_after: u32,
+    /// Consecutive better results needed before the smoothed level improves.
+    pub recover_after: u32,
+    pub margin_pct: u32,
+}
+
+impl Default for Damping {
+    fn default() -> Self {
+        Damping {
+            fail_after: 1,
+            recover_after: 1,
+            margin_pct: 0,
+        }
+    }
+}
+
+impl Damping {
+    pub fn from_query(params: &HashMap<String, String>) -> Self {
+        let get = |key: &str, default: u32| {
+            params
+                .get(key)
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or(default)
+        };
+        let defaults = Damping::default();
+        Damping {
+            fail_after: get("fail_after", defaults.fail_after),
+            recover_after: get("recover_after", defaults.recover_after),
+            margin_pct: get("margin_pct", defaults.margin_pct),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Track {
+    state: Level,
+    candidate: Level,
+    streak: u32,
+}
+
+impl Track {
+    fn observe(&mut self, level: Level, damping: Damping) -> Level {
+        if level == self.state {
+            self.streak = 0;
+            return self.state;
+        }
+        if self.streak > 0 && level == self.candidate {
+            self.streak += 1;
+        } else {
+            self.candidate = level;
+            self.streak = 1;
+        }
+        let needed = if level > self.state {
+            damping.fail_after
+        } else {
+            damping.recover_after
+        };
+        if self.streak >= needed.max(1) {
+            self.state = level;
+            self.streak = 0;
+        }
+        self.state
+    }
+}
+
+/// Smoothed levels per check instance, keyed by the caller (e.g. handler, RPC and
+/// address) together with the thresholds and damping, so repeated probes of the same
+/// thing share history and probes with different settings do not.
+#[derive(Default)]
+pub struct HysteresisStore {
+    /// Each track with the sequence number of its latest probe.
+    tracks: Mutex<HashMap<String, (Track, u64)>>,
+    probes: AtomicU64,
+}
+
+impl HysteresisStore {
+    /// Returns (raw, smoothed): the raw level ignores history, the smoothed one
+    /// applies the recovery margin and the consecutive-result requirements.
+    pub fn evaluate(
+        &self,
+        key: &str,
+        value: &BigInt,
+        thresholds: &Thresholds,
+        damping: Damping,
+    ) -> (Level, Level) {
+        let key = format!(
+            "{}|{:?}|{:?}|{}|{:?}",
+            key, thresholds.direction, thresholds.warn, thresholds.critical, damping
+        );
+        let mut tracks = self.tracks.lock().unwrap();
+        if tracks.len() >= MAX_TRACKS && !tracks.contains_key(&key) {
+            let oldest = tracks
+                .iter()
+                .min_by_key(|(_, (_, seen))| *seen)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                tracks.remove(&oldest);
+            }
+        }
+        let (track, seen) = tracks.entry(key).or_default();
+        *seen = self.probes.fetch_add(1, Ordering::Relaxed);
+        let raw = thresholds.level(value, Level::Ok);
+        let with_margin = thresholds.level(value, track.state);
+        (raw, track.observe(with_margin, damping))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lag_thresholds(margin_pct: u32) -> Thresholds {
+        Thresholds {
+            warn: Some(BigInt::from(50)),
+            critical: BigInt::from(100),
+            direction: Direction::AtOrAbove,
+            margin_pct,
+        }
+    }
+
+    fn damping(fail_after: u32, recover_after: u32) -> Damping {
+        Damping {
+            fail_after,
+            recover_after,
+            margin_pct: 0,
+        }
+    }
+
+    #[test]
+    fn test_raw_levels() {
+        let t = lag_thresholds(0);
+        assert_eq!(t.level(&BigInt::from(49), Level::Ok), Level::Ok);
+        assert_eq!(t.level(&BigInt::from(50), Level::Ok), Level::Warning);
+        assert_eq!(t.level(&BigInt::from(100), Level::Ok), Level::Critical);
+
+        let balance = Thresholds {
+            warn: None,
+            critical: BigInt::from(10),
+            direction: Direction::AtOrBelow,
+            margin_pct: 0,
+        };
+        assert_eq!(balance.level(&BigInt::from(10), Level::Ok), Level::Critical);
+        assert_eq!(balance.level(&BigInt::from(11), Level::Ok), Level::Ok);
+    }
+
+    #[test]
+    fn test_recovery_margin_holds_level() {
+        let t = lag_thresholds(10);
+        // Entering needs the plain threshold; leaving needs to clear it by 10%.
+        assert_eq!(t.level(&BigInt::from(95), Level::Ok), Level::Warning);
+        assert_eq!(t.level(&BigInt::from(95), Level::Critical), Level::Critical);
+        assert_eq!(t.level(&BigInt::from(89), Level::Critical), Level::Warning);
+        assert_eq!(t.level(&BigInt::from(46), Level::Warning), Level::Warning);
+        assert_eq!(t.level(&BigInt::from(44), Level::Warning), Level::Ok);
+    }
+
+    #[test]
+    fn test_fail_after_and_recover_after() {
+        let store = HysteresisStore::default();
+        let t = lag_thresholds(0);
+        let d = damping(3, 2);
+        let probe = |v: i64| store.evaluate("k", &BigInt::from(v), &t, d);
+
+        assert_eq!(probe(120), (Level::Critical, Level::Ok));
+        assert_eq!(probe(120), (Level::Critical, Level::Ok));
+        assert_eq!(probe(120), (Level::Critical, Level::Critical));
+        assert_eq!(probe(10), (Level::Ok, Level::Critical));
+        assert_eq!(probe(10), (Level::Ok, Level::Ok));
+    }
+
+    #[test]
+    fn test_interrupted_streak_resets() {
+        let store = HysteresisStore::default();
+        let t = lag_thresholds(0);
+        let d = damping(2, 1);
+        let probe = |v: i64| store.evaluate("k", &BigInt::from(v), &t, d).1;
+
+        assert_eq!(probe(120), Level::Ok);
+        assert_eq!(probe(10), Level::Ok);
+        assert_eq!(probe(120), Level::Ok);
+        assert_eq!(probe(120), Level::Critical);
+        // Separate keys keep separate history.
+        assert_eq!(
+            store.evaluate("other", &BigInt::from(120), &t, d).1,
+            Level::Ok
+        );
+    }
+
+    #[test]
+    fn test_different_settings_keep_separate_history() {
+        let store = HysteresisStore::default();
+        let d = damping(2, 1);
+        assert_eq!(
+            store
+                .evaluate("k", &BigInt::from(120), &lag_thresholds(0), d)
+                .1,
+            Level::Ok
+        );
+        // Same key, other thresholds or damping: the first streak does not carry over.
+        let tighter = Thresholds {
+            critical: BigInt::from(110),
+            ..lag_thresholds(0)
+        };
+        assert_eq!(
+            store.evaluate("k", &BigInt::from(120), &tighter, d).1,
+            Level::Ok
+        );
+        assert_eq!(
+            store
+                .evaluate("k", &BigInt::from(120), &lag_thresholds(0), damping(3, 1))
+                .1,
+            Level::Ok
+        );
+        assert_eq!(
+            store
+                .evaluate("k", &BigInt::from(120), &lag_thresholds(0), d)
+                .1,
+            Level::Critical
+        );
+    }
+
+    #[test]
+    fn test_store_is_capped() {
+        let store = HysteresisStore::default();
+        let t = lag_thresholds(0);
+        let d = damping(2, 1);
+        store.evaluate("first", &BigInt::from(120), &t, d);
+        for i in 0..MAX_TRACKS {
+            store.evaluate(&i.to_string(), &BigInt::from(0), &t, d);
+        }
+        assert_eq!(store.tracks.lock().unwrap().len(), MAX_TRACKS);
+        // The least recently probed track was dropped, so its streak starts over.
+        assert_eq!(
+            store.evaluate("first", &BigInt::from(120), &t, d).1,
+            Level::Ok
+        );
+    }
+
+    #[test]
+    fn test_damping_from_query() {
+        let mut params = HashMap::new();
+        params.insert("fail_after".to_string(), "3".to_string());
+        params.insert("margin_pct".to_string(), "bad".to_string());
+        let d = Damping::from_query(&params);
+        assert_eq!(d.fail_after, 3);
+        assert_eq!(d.recover_after, 1);
+        assert_eq!(d.margin_pct, 0);
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashSet;
+use std::env;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use warp::http::{Request, Response, StatusCode};
+use warp::hyper::Body;
+
+use crate::config::Config;
+
+/// URL scheme selecting the IPC transport, e.g. `ipc:///var/run/geth/geth.ipc`.
+pub const SCHEME: &str = "ipc://";
+
+/// IPC sockets HTTP callers may name in a query parameter such as `rpc=`: those of
+/// configured targets plus the comma-separated `IPC_SOCKETS` list. Any other socket
+/// is refused, or callers could make the monitor write to arbitrary local sockets.
+pub struct IpcAllowlist {
+    urls: HashSet<String>,
+}
+
+impl IpcAllowlist {
+    pub fn from_env(config: &Config) -> Self {
+        let listed = env::var("IPC_SOCKETS").unwrap_or_default();
+        Self::new(config, &listed)
+    }
+
+    fn new(config: &Config, listed: &str) -> Self {
+        let mut urls: HashSet<String> = config
+            .targets
+            .iter()
+            .map(|t| t.rpc.clone())
+            .filter(|rpc| rpc.starts_with(SCHEME))
+            .collect();
+        urls.extend(
+            listed
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| {
+                    if s.starts_with(SCHEME) {
+                        s.to_string()
+                    } else {
+                        format!("{}{}", SCHEME, s)
+                    }
+                }),
+        );
+        IpcAllowlist { urls }
+    }
+
+    /// The 400 response to reject `req` with when a query parameter names an IPC
+    /// socket that is not allowed, or `None` when it may proceed.
+    pub fn deny(&self, req: &Request<Body>) -> Option<Response<Body>> {
+        let query = req.uri().query()?;
+        let url = reqwest::Url::parse(&format!("http://localhost/?{}", query)).ok()?;
+        let (name, _) = url
+            .query_pairs()
+            .find(|(_, value)| value.starts_with(SCHEME) && !self.urls.contains(&**value))?;
+        Some(crate::auth::error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "error: {} names an IPC socket that is not configured; list it in IPC_SOCKETS",
+                name
+            ),
+        ))
+    }
+}
+
+/// Largest response accepted; a batch of full blocks with transactions runs to a few MB.
+const MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Send one JSON-RPC payload (a request or a batch) over a node's Unix-domain IPC
+/// socket and return the response, as `post_rpc` does for HTTP. geth, op-geth and
+/// reth speak newline-delimited JSON.
+pub async fn request(path: &str, payload: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut stream = UnixStream::connect(path)
+        .await
+        .map_err(|e| format!("failed to connect to IPC socket '{}': {}", path, e))?;
+    stream.write_all(payload).await?;
+    stream.write_all(b"\n").await?;
+    read_response(&mut stream, MAX_RESPONSE_BYTES).await
+}
+
+/// Read one response line of at most `limit` bytes. Only newly read bytes are scanned
+/// for the newline. A node that closes the connection instead of ending the line is
+/// accepted if what it sent parses.
+async fn read_response(
+    reader: &mut (impl AsyncRead + Unpin),
+    limit: usize,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut response = Vec::new();
+    let mut chunk = [0u8; 8192];
+    loop {
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            if serde_json::from_slice::<serde::de::IgnoredAny>(&response).is_ok() {
+                return Ok(String::from_utf8(response)?);
+            }
+            return Err("IPC connection closed before a complete response".into());
+        }
+        let scanned = response.len();
+        response.extend_from_slice(&chunk[..read]);
+        if let Some(newline) = response[scanned..].iter().position(|b| *b == b'\n') {
+            response.truncate(scanned + newline);
+            if response.len() > limit {
+                break;
+            }
+            if response.last() == Some(&b'\r') {
+                response.pop();
+            }
+            return Ok(String::from_utf8(response)?);
+        }
+        if response.len() > limit {
+            break;
+        }
+    }
+    Err(format!("IPC response exceeds {} bytes", limit).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempPath;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::UnixListener;
+
+    /// A socket path unique to this test process and `name`, removed when dropped.
+    fn socket_path(name: &str) -> TempPath {
+        TempPath::new(&format!("{}.ipc", name))
+    }
+
+    /// Serve one connection: read a request line and answer with `reply(request)`,
+    /// written in two halves to exercise reassembly.
+    fn serve_once(path: &str, reply: impl Fn(serde_json::Value) -> String + Send + 'static) {
+        let listener = UnixListener::bind(path).unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut line = String::new();
+            BufReader::new(read).read_line(&mut line).await.unwrap();
+            let body = reply(serde_json::from_str(&line).unwrap());
+            let (head, tail) = body.split_at(body.len() / 2);
+            write.write_all(head.as_bytes()).await.unwrap();
+            write.flush().await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            write.write_all(tail.as_bytes()).await.unwrap();
+            write.write_all(b"\n").await.unwrap();
+        });
+    }
+
+    #[tokio::test]
+    async fn test_request_reads_a_split_response() {
+        let path = socket_path("split");
+        serve_once(path.as_str(), |request| {
+            serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": "0x10"}).to_string()
+        });
+
+        let response = request(
+            path.as_str(),
+            br#"{"jsonrpc":"2.0","id":7,"method":"eth_blockNumber","params":[]}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response, r#"{"id":7,"jsonrpc":"2.0","result":"0x10"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_helpers_accept_ipc_urls() {
+        let path = socket_path("helpers");
+        let url = format!("{}{}", SCHEME, path.as_str());
+        let reply = |result: serde_json::Value| {
+            move |request: serde_json::Value| {
+                serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": result})
+                    .to_string()
+            }
+        };
+
+        serve_once(path.as_str(), reply(serde_json::json!("0x10")));
+        assert_eq!(
+            crate::get_block_number(Some(url.clone())).await.unwrap(),
+            16
+        );
+
+        let _ = std::fs::remove_file(&*path);
+        serve_once(path.as_str(), reply(serde_json::json!({"number": "0x20"})));
+        assert_eq!(
+            crate::get_block_header_by_tag(Some(url.clone()), "latest")
+                .await
+                .unwrap()
+                .number,
+            32
+        );
+
+        let _ = std::fs::remove_file(&*path);
+        serve_once(path.as_str(), reply(serde_json::json!("0xde0b6b3a7640000")));
+        assert_eq!(
+            crate::get_balance(Some(url), "0x1".to_string())
+                .await
+                .unwrap()
+                .to_string(),
+            "1000000000000000000"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_response_stops_at_the_newline() {
+        let mut reader = &b"{\"id\":1}\r\n{\"id\":2}\n"[..];
+        assert_eq!(
+            read_response(&mut reader, 1024).await.unwrap(),
+            r#"{"id":1}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_response_accepts_a_closed_connection_without_newline() {
+        let mut reader = &br#"{"id":1}"#[..];
+        assert_eq!(
+            read_response(&mut reader, 1024).await.unwrap(),
+            r#"{"id":1}"#
+        );
+
+        let mut truncated = &br#"{"id":"#[..];
+        let err = read_response(&mut truncated, 1024).await.unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("closed before a complete response"));
+    }
+
+    #[tokio::test]
+    async fn test_read_response_is_capped() {
+        let line = format!("\"{}\"\n", "a".repeat(100_000));
+        let mut reader = line.as_bytes();
+        let err = read_response(&mut reader, 10_000).await.unwrap_err();
+        assert!(err.to_string().contains("exceeds 10000 bytes"), "{}", err);
+
+        let mut reader = line.as_bytes();
+        assert!(read_response(&mut reader, 200_000).await.is_ok());
+    }
+
+    #[test]
+    fn test_only_configured_sockets_are_allowed() {
+        let config = crate::config::parse_config(
+            r#"{"targets":[{"name":"l1","rpc":"ipc:///data/geth.ipc"},{"name":"op","rpc":"http://x"}]}"#,
+        )
+        .unwrap();
+        let allowlist = IpcAllowlist::new(&config, "/run/reth.ipc, ipc:///run/op.ipc");
+        let status = |uri: &str| {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            allowlist.deny(&req).map_or(StatusCode::OK, |r| r.status())
+        };
+
+        assert_eq!(status("/?rpc=ipc:///data/geth.ipc"), StatusCode::OK);
+        assert_eq!(status("/?rpc=ipc:///run/reth.ipc"), StatusCode::OK);
+        assert_eq!(
+            status("/proposal_lag?l1_rpc=ipc:///run/op.ipc"),
+            StatusCode::OK
+        );
+        assert_eq!(status("/?rpc=http://10.0.0.1:8545"), StatusCode::OK);
+        assert_eq!(status("/gas"), StatusCode::OK);
+        assert_eq!(
+            status("/?rpc=ipc:///var/run/docker.sock"),
+            StatusCode::BAD_REQUEST
+        );
+        // Encoded values are decoded before the check.
+        assert_eq!(
+            status("/proposal_lag?l1_rpc=ipc%3A%2F%2F%2Fvar%2Frun%2Fdocker.sock"),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_to_missing_socket_fails() {
+        let err = request(socket_path("missing").as_str(), b"{}")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("failed to connect to IPC socket"));
+    }
+}
@@ -0,0 +1,130 @@
+use tokio::time::Duration;
+
+use crate::config::KumaPush;
+use crate::state::CheckResult;
+
+/// Push URL for `result`: `<url>/api/push/<token>?status=up|down&msg=<status>&ping=<ms>`.
+fn push_url(config: &KumaPush, token: &str, result: &CheckResult) -> Option<reqwest::Url> {
+    let status = if result.healthy { "up" } else { "down" };
+    reqwest::Url::parse_with_params(
+        &format!("{}/api/push/{}", config.url.trim_end_matches('/'), token),
+        &[
+            ("status", status.to_string()),
+            ("msg", result.status.clone()),
+            ("ping", result.latency_ms.to_string()),
+        ],
+    )
+    .ok()
+}
+
+/// Push `result` to its Kuma monitor when the target maps the check to a token.
+/// Failures are only logged: Kuma marks the monitor down itself once pushes stop.
+pub async fn push(client: &reqwest::Client, config: &KumaPush, result: &CheckResult) {
+    let token = match config.tokens.get(&result.check) {
+        Some(t) => t,
+        None => return,
+    };
+    let url = match push_url(config, token, result) {
+        Some(u) => u,
+        None => {
+            tracing::warn!(target = %result.target, check = %result.check, "kuma push url is invalid");
+            return;
+        }
+    };
+
+    let outcome = client
+        .get(url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    if let Err(e) = outcome {
+        // The push URL carries the monitor's token.
+        tracing::warn!(target = %result.target, check = %result.check, error = %e.without_url(), "kuma push failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+    use std::collections::HashMap;
+
+    fn result(healthy: bool) -> CheckResult {
+        CheckResult {
+            target: "l1".to_string(),
+            check: "sync".to_string(),
+            healthy,
+            status: if healthy { "synced" } else { "not synced" }.to_string(),
+            detail: serde_json::Value::Null,
+            checked_at: 0,
+            latency_ms: 42,
+        }
+    }
+
+    fn kuma(url: &str) -> KumaPush {
+        KumaPush {
+            url: url.to_string(),
+            tokens: HashMap::from([("sync".to_string(), "tok".to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_push_url_encodes_status() {
+        let url = push_url(&kuma("http://kuma:3001/"), "tok", &result(false)).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "http://kuma:3001/api/push/tok?status=down&msg=not+synced&ping=42"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_push_sends_mapped_checks_only() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/push/tok")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("status".to_string(), "up".to_string()),
+                Matcher::UrlEncoded("msg".to_string(), "synced".to_string()),
+                Matcher::UrlEncoded("ping".to_string(), "42".to_string()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"ok":true}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = reqwest::Client::new();
+        let config = kuma(&server.url());
+        push(&client, &config, &result(true)).await;
+        let mut unmapped = result(true);
+        unmapped.check = "finalized_latest_diff".to_string();
+        push(&client, &config, &unmapped).await;
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_push_does_not_leak_the_token() {
+        let (_guard, logs) = crate::tests::capture_logs();
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/api/push/SECRET")
+            .match_query(Matcher::Any)
+            .with_status(500)
+            .create_async()
+            .await;
+        let mut config = kuma(&server.url());
+        config.tokens.insert("sync".to_string(), "SECRET".to_string());
+
+        let client = reqwest::Client::new();
+        push(&client, &config, &result(true)).await;
+        // Transport errors carry the URL too.
+        config.url = "http://127.0.0.1:1".to_string();
+        push(&client, &config, &result(true)).await;
+
+        let logs = String::from_utf8(logs.lock().unwrap().clone()).unwrap();
+        assert_eq!(logs.matches("kuma push failed").count(), 2, "{}", logs);
+        assert!(!logs.contains("SECRET"), "{}", logs);
+    }
+}
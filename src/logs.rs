@@ -0,0 +1,382 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::config::{Config, LogAlert, LogWatcher};
+use crate::state::{CheckResult, MonitorState};
+use crate::{encode_hex, get_block_number, keccak256, rpc_call};
+
+/// Most providers cap `eth_getLogs` ranges; larger gaps are scanned in chunks.
+const MAX_BLOCK_RANGE: i64 = 1000;
+
+/// Per-watcher scan progress kept by the background poller between rounds.
+pub struct WatcherState {
+    next_block: Option<i64>,
+    started_at: u64,
+    /// (unix seconds observed, matching logs) per scanned chunk with matches.
+    matches: VecDeque<(u64, u64)>,
+    total_events: u64,
+}
+
+impl WatcherState {
+    pub fn new(now: u64) -> Self {
+        WatcherState {
+            next_block: None,
+            started_at: now,
+            matches: VecDeque::new(),
+            total_events: 0,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WatcherDetail {
+    contract: String,
+    event: String,
+    last_scanned_block: Option<i64>,
+    events_in_window: u64,
+    window_minutes: u64,
+    total_events: u64,
+}
+
+pub fn check_name(watcher: &LogWatcher) -> String {
+    format!("log_watcher:{}", watcher.name)
+}
+
+/// topic0 for an event: `keccak256` of its canonical signature.
+fn event_topic(event: &str) -> String {
+    format!("0x{}", encode_hex(&keccak256(event.as_bytes())))
+}
+
+fn topic_filter(watcher: &LogWatcher) -> Vec<Value> {
+    let mut topics = vec![Value::String(event_topic(&watcher.event))];
+    for topic in &watcher.topics {
+        topics.push(match topic {
+            Some(t) => Value::String(t.clone()),
+            None => Value::Null,
+        });
+    }
+    topics
+}
+
+async fn get_log_count(
+    rpc_url: &str,
+    watcher: &LogWatcher,
+    from_block: i64,
+    to_block: i64,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let filter = serde_json::json!({
+        "address": watcher.contract,
+        "topics": topic_filter(watcher),
+        "fromBlock": format!("0x{:x}", from_block),
+        "toBlock": format!("0x{:x}", to_block),
+    });
+    let result = rpc_call(Some(rpc_url.to_string()), "eth_getLogs", vec![filter]).await?;
+    let logs = result
+        .as_array()
+        .ok_or("eth_getLogs result is not an array")?;
+    Ok(logs.len() as u64)
+}
+
+/// Scan blocks that reached `confirmations` depth since the previous round. The first
+/// round starts there rather than backfilling history.
+pub async fn scan(
+    rpc_url: &str,
+    watcher: &LogWatcher,
+    state: &mut WatcherState,
+    now: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let head = get_block_number(Some(rpc_url.to_string())).await?;
+    let latest = head.saturating_sub(watcher.confirmations as i64).max(0);
+    let mut from = state.next_block.unwrap_or(latest);
+
+    while from <= latest {
+        let to = (from + MAX_BLOCK_RANGE - 1).min(latest);
+        let count = get_log_count(rpc_url, watcher, from, to).await?;
+        if count > 0 {
+            state.matches.push_back((now, count));
+            state.total_events += count;
+        }
+        // Only advance once the chunk succeeded so a failed round is retried.
+        state.next_block = Some(to + 1);
+        from = to + 1;
+    }
+
+    let window_start = now.saturating_sub(watcher.window_minutes * 60);
+    while let Some((seen_at, _)) = state.matches.front() {
+        if *seen_at >= window_start {
+            break;
+        }
+        state.matches.pop_front();
+    }
+
+    Ok(())
+}
+
+/// Apply the watcher's alert rule to the events seen within its window.
+pub fn evaluate(target: &str, watcher: &LogWatcher, state: &WatcherState, now: u64) -> CheckResult {
+    let window_secs = watcher.window_minutes * 60;
+    let window_start = now.saturating_sub(window_secs);
+    let events_in_window: u64 = state
+        .matches
+        .iter()
+        .filter(|(seen_at, _)| *seen_at >= window_start)
+        .map(|(_, count)| count)
+        .sum();
+
+    let (healthy, status) = match &watcher.alert {
+        // Absence can only be judged once a full window has been observed.
+        LogAlert::Absence
+            if events_in_window == 0 && now.saturating_sub(state.started_at) >= window_secs =>
+        {
+            (false, "no_events".to_string())
+        }
+        LogAlert::MaxRate { max_events } if events_in_window > *max_events => {
+            (false, "rate_exceeded".to_string())
+        }
+        LogAlert::AnyOccurrence if events_in_window > 0 => (false, "event_seen".to_string()),
+        _ => (true, "ok".to_string()),
+    };
+
+    let detail = WatcherDetail {
+        contract: watcher.contract.clone(),
+        event: watcher.event.clone(),
+        last_scanned_block: state.next_block.map(|n| n - 1),
+        events_in_window,
+        window_minutes: watcher.window_minutes,
+        total_events: state.total_events,
+    };
+
+    CheckResult {
+        target: target.to_string(),
+        check: check_name(watcher),
+        healthy,
+        status,
+        detail: serde_json::to_value(detail).unwrap_or(Value::Null),
+        checked_at: now,
+        latency_ms: 0,
+    }
+}
+
+/// HTTP handler for `/log_watcher?target=<name>&name=<watcher>`: reports the latest
+/// result from the background poller. 503 until the first round has completed.
+pub async fn check_log_watcher(
+    config: Arc<Config>,
+    state: Arc<MonitorState>,
+    target: String,
+    name: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let watcher = match config.target(&target).and_then(|t| t.log_watcher(&name)) {
+        Some(w) => w,
+        None => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&serde_json::json!({
+                    "target": target,
+                    "check": format!("log_watcher:{}", name),
+                    "status": format!("error: no log watcher '{}' on target '{}'", name, target),
+                })),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    };
+
+    match state.get(&target, &check_name(watcher)) {
+        Some(result) => {
+            let status = if result.healthy {
+                warp::http::StatusCode::OK
+            } else {
+                warp::http::StatusCode::SERVICE_UNAVAILABLE
+            };
+            Ok(warp::reply::with_status(warp::reply::json(&result), status))
+        }
+        None => Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "target": target,
+                "check": check_name(watcher),
+                "status": "pending",
+            })),
+            warp::http::StatusCode::SERVICE_UNAVAILABLE,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::echo_rpc_id;
+    use mockito::{Matcher, Server};
+
+    fn watcher(alert: LogAlert) -> LogWatcher {
+        LogWatcher {
+            name: "ownership".to_string(),
+            contract: "0x0000000000000000000000000000000000000001".to_string(),
+            event: "OwnershipTransferred(address,address)".to_string(),
+            topics: vec![None],
+            window_minutes: 10,
+            confirmations: 0,
+            alert,
+            severity: Default::default(),
+        }
+    }
+
+    async fn mock_head(server: &mut mockito::ServerGuard, block: u64) -> mockito::Mock {
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"method":"eth_blockNumber"}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":"0x{:x}"}}"#,
+                block
+            )))
+            .create_async()
+            .await
+    }
+
+    async fn mock_logs(
+        server: &mut mockito::ServerGuard,
+        from: &str,
+        to: &str,
+        count: usize,
+    ) -> mockito::Mock {
+        let logs = vec![r#"{"blockNumber":"0x1"}"#; count].join(",");
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(format!(
+                r#"{{"method":"eth_getLogs","params":[{{"fromBlock":"{}","toBlock":"{}"}}]}}"#,
+                from, to
+            )))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":[{}]}}"#,
+                logs
+            )))
+            .create_async()
+            .await
+    }
+
+    #[test]
+    fn test_event_topic() {
+        assert_eq!(
+            event_topic("Transfer(address,address,uint256)"),
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+    }
+
+    #[test]
+    fn test_topic_filter_keeps_wildcards() {
+        let mut w = watcher(LogAlert::AnyOccurrence);
+        let topic = format!("0x{}", "01".repeat(32));
+        w.topics = vec![None, Some(topic.clone())];
+        let topics = topic_filter(&w);
+        assert_eq!(topics.len(), 3);
+        assert!(topics[1].is_null());
+        assert_eq!(topics[2], topic);
+    }
+
+    #[tokio::test]
+    async fn test_scan_is_incremental() {
+        let mut server = Server::new_async().await;
+        let w = watcher(LogAlert::AnyOccurrence);
+        let mut state = WatcherState::new(0);
+
+        let head = mock_head(&mut server, 100).await;
+        let first = mock_logs(&mut server, "0x64", "0x64", 0).await;
+        scan(&server.url(), &w, &mut state, 0).await.unwrap();
+        first.assert_async().await;
+        head.remove_async().await;
+
+        mock_head(&mut server, 105).await;
+        let second = mock_logs(&mut server, "0x65", "0x69", 2).await;
+        scan(&server.url(), &w, &mut state, 60).await.unwrap();
+        second.assert_async().await;
+
+        let result = evaluate("l1", &w, &state, 60);
+        assert!(!result.healthy);
+        assert_eq!(result.status, "event_seen");
+        assert_eq!(result.detail["events_in_window"], 2);
+        assert_eq!(result.detail["last_scanned_block"], 105);
+    }
+
+    #[tokio::test]
+    async fn test_scan_stays_confirmations_behind_the_head() {
+        let mut server = Server::new_async().await;
+        let mut w = watcher(LogAlert::AnyOccurrence);
+        w.confirmations = 3;
+        let mut state = WatcherState::new(0);
+        state.next_block = Some(90);
+
+        let head = mock_head(&mut server, 100).await;
+        let logs = mock_logs(&mut server, "0x5a", "0x61", 1).await;
+        scan(&server.url(), &w, &mut state, 0).await.unwrap();
+        logs.assert_async().await;
+        assert_eq!(state.next_block, Some(98));
+        head.remove_async().await;
+
+        // Near genesis the scan starts at block 0 rather than a negative height.
+        let mut state = WatcherState::new(0);
+        mock_head(&mut server, 1).await;
+        let genesis = mock_logs(&mut server, "0x0", "0x0", 0).await;
+        scan(&server.url(), &w, &mut state, 0).await.unwrap();
+        genesis.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_scan_failure_does_not_advance() {
+        let mut server = Server::new_async().await;
+        let w = watcher(LogAlert::AnyOccurrence);
+        let mut state = WatcherState::new(0);
+        state.next_block = Some(50);
+
+        mock_head(&mut server, 60).await;
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"method":"eth_getLogs"}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32005,"message":"query returned more than 10000 results"}}"#))
+            .create_async()
+            .await;
+
+        assert!(scan(&server.url(), &w, &mut state, 0).await.is_err());
+        assert_eq!(state.next_block, Some(50));
+    }
+
+    #[test]
+    fn test_evaluate_absence_waits_for_full_window() {
+        let w = watcher(LogAlert::Absence);
+        let state = WatcherState::new(1_000);
+        assert!(evaluate("l1", &w, &state, 1_300).healthy);
+        let result = evaluate("l1", &w, &state, 1_600);
+        assert!(!result.healthy);
+        assert_eq!(result.status, "no_events");
+    }
+
+    #[test]
+    fn test_evaluate_absence_recovers_when_event_in_window() {
+        let w = watcher(LogAlert::Absence);
+        let mut state = WatcherState::new(0);
+        state.matches.push_back((1_000, 1));
+        assert!(evaluate("l1", &w, &state, 1_500).healthy);
+        // The event ages out of the 10-minute window.
+        assert!(!evaluate("l1", &w, &state, 1_601).healthy);
+    }
+
+    #[test]
+    fn test_evaluate_max_rate() {
+        let w = watcher(LogAlert::MaxRate { max_events: 3 });
+        let mut state = WatcherState::new(0);
+        state.matches.push_back((100, 3));
+        assert!(evaluate("l1", &w, &state, 100).healthy);
+        state.matches.push_back((110, 1));
+        let result = evaluate("l1", &w, &state, 110);
+        assert!(!result.healthy);
+        assert_eq!(result.status, "rate_exceeded");
+    }
+}
@@ -1,13 +1,43 @@
-use rand::Rng;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::env;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::Instant;
 use tokio::time::{sleep, Duration};
 use warp::Filter;
-use num_bigint::BigUint;
+use num_bigint::{BigInt, BigUint};
 use num_traits::Num;
 use std::str::FromStr;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::Instrument;
+
+mod assertions;
+mod auth;
+mod batches;
+mod beacon;
+mod blob;
+mod cache;
+mod config;
+mod dashboard;
+mod gas;
+mod heads;
+mod health;
+mod history;
+mod hysteresis;
+mod ipc;
+mod kuma;
+mod logs;
+mod notify;
+mod poller;
+mod probes;
+mod proposals;
+mod ratelimit;
+mod state;
+mod telemetry;
+mod tls;
+mod txpool;
 
 /// Strip an optional hex prefix from a hex string without panicking.
 /// Slicing `s[2..]` blindly panics when the RPC returns a value shorter than
@@ -32,23 +62,244 @@ struct RpcRequest {
     jsonrpc: String,
     method: String,
     params: Vec<serde_json::Value>,
-    id: u32,
+    id: u64,
 }
 
+static NEXT_RPC_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Process-wide, strictly increasing JSON-RPC request id, so concurrent calls
+/// sharing a connection or proxy never reuse one.
+fn next_rpc_id() -> u64 {
+    NEXT_RPC_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A JSON-RPC response whose `id` is not the one its request was sent with, e.g. a
+/// proxy or load balancer handing back another caller's reply.
+#[derive(Debug)]
+struct RpcIdMismatch {
+    method: String,
+    expected: u64,
+    actual: Option<u64>,
+}
+
+impl fmt::Display for RpcIdMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let actual = self
+            .actual
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| "null".to_string());
+        write!(
+            f,
+            "{} response id mismatch: sent {}, received {}",
+            self.method, self.expected, actual
+        )
+    }
+}
+
+impl std::error::Error for RpcIdMismatch {}
+
+/// Check a response `id` against the request's. A null `id` is only accepted on
+/// error responses, which JSON-RPC 2.0 allows when the request id could not be read.
+fn verify_rpc_id(
+    method: &str,
+    expected: u64,
+    actual: Option<u64>,
+    is_error: bool,
+) -> Result<(), RpcIdMismatch> {
+    if actual == Some(expected) || (actual.is_none() && is_error) {
+        return Ok(());
+    }
+    tracing::warn!(rpc.method = method, expected, actual, outcome = "id_mismatch", "rpc call");
+    Err(RpcIdMismatch {
+        method: method.to_string(),
+        expected,
+        actual,
+    })
+}
+
+const DEFAULT_RPC_URL: &str = "http://127.0.0.1:8545";
+/// Upper bound on one JSON-RPC round-trip, over HTTP or IPC, and on one beacon API call.
+const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// POST a JSON-RPC request and return the response body. Runs in an `rpc` span,
+/// tags the call with the current correlation ID and logs its method, latency and
+/// outcome.
+async fn post_rpc(
+    rpc_url: Option<String>,
+    payload: &RpcRequest,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let rpc = rpc_url.unwrap_or_else(|| DEFAULT_RPC_URL.to_string());
+    let span = tracing::info_span!(
+        "rpc",
+        otel.kind = "client",
+        rpc.system = "jsonrpc",
+        rpc.method = %payload.method,
+        rpc.jsonrpc.request_id = payload.id,
+        target = %telemetry::rpc_target(&rpc),
+        outcome = tracing::field::Empty,
+    );
+    let (_, body) = send_rpc(&rpc, &payload.method, payload)
+        .instrument(span)
+        .await?;
+    Ok(body)
+}
+
+/// POST a JSON-RPC batch array and return the HTTP status and response body, like
+/// `post_rpc`.
+async fn post_rpc_batch(
+    rpc: &str,
+    payload: &[RpcRequest],
+) -> Result<(warp::http::StatusCode, String), Box<dyn std::error::Error>> {
+    let methods = payload
+        .iter()
+        .map(|r| r.method.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    let span = tracing::info_span!(
+        "rpc",
+        otel.kind = "client",
+        rpc.system = "jsonrpc",
+        rpc.method = "batch",
+        rpc.batch.methods = %methods,
+        rpc.batch.size = payload.len(),
+        target = %telemetry::rpc_target(rpc),
+        outcome = tracing::field::Empty,
+    );
+    send_rpc(rpc, "batch", payload).instrument(span).await
+}
+
+async fn send_rpc<T: Serialize + ?Sized>(
+    rpc: &str,
+    method: &str,
+    payload: &T,
+) -> Result<(warp::http::StatusCode, String), Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    let result = match rpc.strip_prefix(ipc::SCHEME) {
+        Some(path) => {
+            let body = serde_json::to_vec(payload)?;
+            match tokio::time::timeout(RPC_TIMEOUT, ipc::request(path, &body)).await {
+                Ok(Ok(response)) => Ok((warp::http::StatusCode::OK, response)),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err(format!("IPC request timed out after {}s", RPC_TIMEOUT.as_secs())),
+            }
+        }
+        None => {
+            let mut request = Client::new().post(rpc).timeout(RPC_TIMEOUT).json(payload);
+            if let Some(id) = telemetry::request_id() {
+                request = request.header(telemetry::REQUEST_ID_HEADER, id);
+            }
+            async {
+                let response = request.send().await?;
+                let status = response.status();
+                Ok::<_, reqwest::Error>((status, response.text().await?))
+            }
+            .await
+            // reqwest errors carry the URL, and RPC URLs can hold provider API keys.
+            .map_err(|e| e.without_url().to_string())
+        }
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let (outcome, error) = match &result {
+        Ok((status, _)) if !status.is_success() => ("http_error", Some(status.to_string())),
+        Ok((_, body)) => match serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|v| v.get("error").filter(|e| !e.is_null()).map(|e| e.to_string()))
+        {
+            Some(error) => ("rpc_error", Some(error)),
+            None => ("ok", None),
+        },
+        Err(e) => ("transport_error", Some(e.clone())),
+    };
+    tracing::Span::current().record("outcome", outcome);
+    match error {
+        Some(error) => tracing::warn!(rpc.method = method, latency_ms, outcome, error, "rpc call"),
+        None => tracing::info!(rpc.method = method, latency_ms, outcome, "rpc call"),
+    }
+
+    Ok(result?)
+}
+
+/// How long a failed `eth_chainId` lookup is remembered before it is retried.
+const CHAIN_ID_RETRY: Duration = Duration::from_secs(60);
+/// URLs whose chain id is remembered; callers pick the URL with `rpc=`, so this is capped.
+const MAX_CHAIN_IDS: usize = 256;
+
+/// Chain ids already looked up, by RPC URL. A failed lookup is kept as `None` for
+/// `CHAIN_ID_RETRY` so an unreachable node is not asked again on every span.
+#[derive(Default)]
+struct ChainIds {
+    ids: HashMap<String, (Option<u64>, Instant)>,
+}
+
+impl ChainIds {
+    /// The cached lookup for `url`, if any is still valid at `now`.
+    fn get(&self, url: &str, now: Instant) -> Option<Option<u64>> {
+        match self.ids.get(url) {
+            Some((Some(id), _)) => Some(Some(*id)),
+            Some((None, failed_at)) if now.duration_since(*failed_at) < CHAIN_ID_RETRY => {
+                Some(None)
+            }
+            _ => None,
+        }
+    }
+
+    /// Remember a lookup, evicting the oldest one at `MAX_CHAIN_IDS`.
+    fn insert(&mut self, url: String, id: Option<u64>, now: Instant) {
+        if self.ids.len() >= MAX_CHAIN_IDS && !self.ids.contains_key(&url) {
+            let oldest = self
+                .ids
+                .iter()
+                .min_by_key(|(_, (_, at))| *at)
+                .map(|(url, _)| url.clone());
+            if let Some(oldest) = oldest {
+                self.ids.remove(&oldest);
+            }
+        }
+        self.ids.insert(url, (id, now));
+    }
+}
+
+static CHAIN_IDS: OnceLock<Mutex<ChainIds>> = OnceLock::new();
+
+/// Record the RPC endpoint's chain id on the current span. The id is fetched once
+/// per URL with `eth_chainId`, and only while spans are exported.
+async fn record_chain_id(rpc_url: &Option<String>) {
+    if !telemetry::otel_enabled() {
+        return;
+    }
+    let url = rpc_url.clone().unwrap_or_else(|| DEFAULT_RPC_URL.to_string());
+    let cache = CHAIN_IDS.get_or_init(Default::default);
+    let cached = cache.lock().unwrap().get(&url, Instant::now());
+    let chain_id = match cached {
+        Some(id) => id,
+        None => {
+            let id = match rpc_call(Some(url.clone()), "eth_chainId", vec![]).await {
+                Ok(serde_json::Value::String(hex)) => {
+                    u64::from_str_radix(strip_hex_prefix(&hex), 16).ok()
+                }
+                _ => None,
+            };
+            cache.lock().unwrap().insert(url, id, Instant::now());
+            id
+        }
+    };
+    if let Some(id) = chain_id {
+        tracing::Span::current().record("chain.id", id);
+    }
+}
+
+// `jsonrpc` is part of the wire format but not inspected by the helpers.
+#[allow(dead_code)]
 #[derive(Deserialize)]
 struct BlockNumberResponse {
     jsonrpc: String,
-    id: u32,
+    id: u64,
     result: String,
 }
 
 async fn get_block_number(rpc_url: Option<String>) -> Result<i64, Box<dyn std::error::Error>> {
-    let current_time = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    let id = rand::thread_rng().gen_range(1..=100);
+    let id = next_rpc_id();
 
     let payload = RpcRequest {
         jsonrpc: "2.0".to_string(),
@@ -57,39 +308,42 @@ async fn get_block_number(rpc_url: Option<String>) -> Result<i64, Box<dyn std::e
         id,
     };
 
-    let rpc = rpc_url.unwrap_or_else(|| "http://127.0.0.1:8545".to_string());
-
-    let client = Client::new();
-    let response = client.post(&rpc).json(&payload).send().await?;
-
-    let body = response.text().await?;
+    let body = post_rpc(rpc_url, &payload).await?;
     let block_response: BlockNumberResponse = serde_json::from_str(&body)?;
-
-    println!(
-        "currentTime: {} ID {} Block Number (Hex): {}",
-        current_time, id, block_response.result
-    );
+    verify_rpc_id(&payload.method, id, Some(block_response.id), false)?;
 
     let block_number = i64::from_str_radix(strip_hex_prefix(&block_response.result), 16)?;
-
-    println!(
-        "currentTime: {} ID {} Block Number (Decimal): {}",
-        current_time, id, block_number
-    );
+    tracing::debug!(block_number, block_hex = %block_response.result, "block number");
 
     Ok(block_number)
 }
 
-async fn get_block_by_tag(
+/// Header fields read from `eth_getBlockByNumber`. Fields introduced by later forks
+/// (Cancun blob gas) are `None` on blocks or chains that predate them.
+#[derive(Debug)]
+struct BlockHeader {
+    number: i64,
+    hash: Option<String>,
+    blob_gas_used: Option<u64>,
+    excess_blob_gas: Option<u64>,
+}
+
+fn optional_hex_u64(
+    block: &serde_json::Value,
+    field: &str,
+) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    match block.get(field).and_then(|v| v.as_str()) {
+        Some(hex) => Ok(Some(u64::from_str_radix(strip_hex_prefix(hex), 16)?)),
+        None => Ok(None),
+    }
+}
+
+/// Fetch a block header by tag (`latest`, `finalized`, ...) or by hex block number.
+async fn get_block_header_by_tag(
     rpc_url: Option<String>,
     tag: &str,
-) -> Result<i64, Box<dyn std::error::Error>> {
-    let current_time = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    let id = rand::thread_rng().gen_range(1..=100);
+) -> Result<BlockHeader, Box<dyn std::error::Error>> {
+    let id = next_rpc_id();
 
     let payload = RpcRequest {
         jsonrpc: "2.0".to_string(),
@@ -101,33 +355,67 @@ async fn get_block_by_tag(
         id,
     };
 
-    let rpc = rpc_url.unwrap_or_else(|| "http://127.0.0.1:8545".to_string());
-
-    let client = Client::new();
-    let response = client.post(&rpc).json(&payload).send().await?;
-
-    let body = response.text().await?;
+    let body = post_rpc(rpc_url, &payload).await?;
     let json_response: serde_json::Value = serde_json::from_str(&body)?;
+    verify_rpc_id(
+        &payload.method,
+        id,
+        json_response.get("id").and_then(|i| i.as_u64()),
+        json_response.get("error").is_some_and(|e| !e.is_null()),
+    )?;
 
-    if let Some(result) = json_response.get("result") {
-        if let Some(number_hex) = result.get("number").and_then(|n| n.as_str()) {
-            println!(
-                "currentTime: {} ID {} Block {} Number (Hex): {}",
-                current_time, id, tag, number_hex
-            );
-
-            let block_number = i64::from_str_radix(strip_hex_prefix(number_hex), 16)?;
-
-            println!(
-                "currentTime: {} ID {} Block {} Number (Decimal): {}",
-                current_time, id, tag, block_number
-            );
-
-            return Ok(block_number);
-        }
+    match json_response.get("result") {
+        Some(result) => parse_block_header(tag, result),
+        None => Err("Block not found or invalid response".into()),
     }
+}
+
+fn parse_block_header(
+    tag: &str,
+    block: &serde_json::Value,
+) -> Result<BlockHeader, Box<dyn std::error::Error>> {
+    let number_hex = block
+        .get("number")
+        .and_then(|n| n.as_str())
+        .ok_or("Block not found or invalid response")?;
+    let block_number = i64::from_str_radix(strip_hex_prefix(number_hex), 16)?;
+    tracing::debug!(tag, block_number, block_hex = %number_hex, "block header");
+
+    Ok(BlockHeader {
+        number: block_number,
+        hash: block
+            .get("hash")
+            .and_then(|h| h.as_str())
+            .map(|h| h.to_string()),
+        blob_gas_used: optional_hex_u64(block, "blobGasUsed")?,
+        excess_blob_gas: optional_hex_u64(block, "excessBlobGas")?,
+    })
+}
 
-    Err("Block not found or invalid response".into())
+/// Fetch several block headers by tag or hex number in one batch request, in the
+/// order given.
+async fn get_block_headers(
+    rpc_url: Option<String>,
+    tags: &[String],
+    rejections: &BatchRejections,
+) -> Result<Vec<BlockHeader>, Box<dyn std::error::Error>> {
+    let calls = tags
+        .iter()
+        .map(|tag| {
+            (
+                "eth_getBlockByNumber",
+                vec![
+                    serde_json::Value::String(tag.clone()),
+                    serde_json::Value::Bool(false),
+                ],
+            )
+        })
+        .collect();
+    let blocks = rpc_batch(rpc_url, calls, rejections).await?;
+    tags.iter()
+        .zip(&blocks)
+        .map(|(tag, block)| parse_block_header(tag, block))
+        .collect()
 }
 
 #[derive(Serialize)]
@@ -144,6 +432,11 @@ struct BlockDiffResponse {
     difference: i64,
     finalized_hex: String,
     latest_hex: String,
+    warn_threshold: Option<i64>,
+    /// Level from this probe alone.
+    raw_state: Option<hysteresis::Level>,
+    /// Level after the recovery margin and fail/recover counts; drives the status code.
+    state: Option<hysteresis::Level>,
 }
 
 #[derive(Deserialize)]
@@ -152,13 +445,15 @@ struct RpcError {
     message: String,
 }
 
+// `jsonrpc` is part of the wire format but not inspected by the helpers.
+#[allow(dead_code)]
 #[derive(Deserialize)]
 struct BalanceResponse {
     jsonrpc: String,
     // JSON-RPC 2.0 allows a null `id` on error responses (e.g. parse errors raised
     // before the request id is read), so this must be optional or deserialization
     // fails and the actual RPC error never surfaces.
-    id: Option<u32>,
+    id: Option<u64>,
     result: Option<String>,
     #[serde(default)]
     error: Option<RpcError>,
@@ -170,10 +465,19 @@ struct CheckBalanceResponse {
     balance: String,
     balance_decimal: String,
     alert_threshold: String,
+    warn_threshold: Option<String>,
+    raw_state: Option<hysteresis::Level>,
+    state: Option<hysteresis::Level>,
     status: String,
 }
 
+#[tracing::instrument(skip_all, fields(
+    target = %telemetry::rpc_target(rpc_url.as_deref().unwrap_or(DEFAULT_RPC_URL)),
+    chain.id = tracing::field::Empty,
+))]
 async fn check_sync(rpc_url: Option<String>) -> Result<impl warp::Reply, warp::Rejection> {
+    record_chain_id(&rpc_url).await;
+
     let block_number_first = match get_block_number(rpc_url.clone()).await {
         Ok(num) => num,
         Err(_) => {
@@ -232,55 +536,64 @@ async fn check_sync(rpc_url: Option<String>) -> Result<impl warp::Reply, warp::R
     }
 }
 
+/// HTTP handler for `/finalized_latest_diff`. `diff` is the critical lag and `warn`
+/// an optional lower warning lag; 503 only once the smoothed state is critical.
+#[tracing::instrument(skip_all, fields(
+    target = %telemetry::rpc_target(rpc_url.as_deref().unwrap_or(DEFAULT_RPC_URL)),
+    chain.id = tracing::field::Empty,
+))]
 async fn finalized_latest_diff(
     rpc_url: Option<String>,
     diff: Option<i64>,
+    warn: Option<i64>,
+    damping: hysteresis::Damping,
+    store: Arc<hysteresis::HysteresisStore>,
+    batch_rejections: Arc<BatchRejections>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let finalized_block = match get_block_by_tag(rpc_url.clone(), "finalized").await {
-        Ok(num) => num,
-        Err(_) => {
-            let error_response = BlockDiffResponse {
-                finalized_block: 0,
-                latest_block: 0,
-                difference: 0,
-                finalized_hex: "".to_string(),
-                latest_hex: "".to_string(),
-            };
-            return Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ));
-        }
+    record_chain_id(&rpc_url).await;
+
+    let mut response = BlockDiffResponse {
+        finalized_block: 0,
+        latest_block: 0,
+        difference: 0,
+        finalized_hex: "".to_string(),
+        latest_hex: "".to_string(),
+        warn_threshold: warn,
+        raw_state: None,
+        state: None,
     };
 
-    let latest_block = match get_block_by_tag(rpc_url, "latest").await {
-        Ok(num) => num,
+    // Both heads in one round-trip, so they are read as close together as possible.
+    let tags = ["finalized".to_string(), "latest".to_string()];
+    let (finalized_block, latest_block) = match get_block_headers(rpc_url.clone(), &tags, &batch_rejections).await {
+        Ok(headers) => (headers[0].number, headers[1].number),
         Err(_) => {
-            let error_response = BlockDiffResponse {
-                finalized_block,
-                latest_block: 0,
-                difference: 0,
-                finalized_hex: format!("0x{:x}", finalized_block),
-                latest_hex: "".to_string(),
-            };
             return Ok(warp::reply::with_status(
-                warp::reply::json(&error_response),
+                warp::reply::json(&response),
                 warp::http::StatusCode::INTERNAL_SERVER_ERROR,
             ));
         }
     };
+    response.finalized_block = finalized_block;
+    response.finalized_hex = format!("0x{:x}", finalized_block);
 
     let difference = latest_block - finalized_block;
-
-    let response = BlockDiffResponse {
-        finalized_block,
-        latest_block,
-        difference,
-        finalized_hex: format!("0x{:x}", finalized_block),
-        latest_hex: format!("0x{:x}", latest_block),
+    response.latest_block = latest_block;
+    response.latest_hex = format!("0x{:x}", latest_block);
+    response.difference = difference;
+
+    let thresholds = hysteresis::Thresholds {
+        warn: warn.map(BigInt::from),
+        critical: BigInt::from(diff.unwrap_or(0)),
+        direction: hysteresis::Direction::AtOrAbove,
+        margin_pct: damping.margin_pct,
     };
+    let key = format!("finalized_latest_diff|{}", rpc_url.unwrap_or_default());
+    let (raw, smoothed) = store.evaluate(&key, &BigInt::from(difference), &thresholds, damping);
+    response.raw_state = Some(raw);
+    response.state = Some(smoothed);
 
-    if difference < diff.unwrap_or(0) {
+    if smoothed < hysteresis::Level::Critical {
         Ok(warp::reply::with_status(
             warp::reply::json(&response),
             warp::http::StatusCode::OK,
@@ -299,7 +612,7 @@ async fn get_balance(
     rpc_url: Option<String>,
     address: String,
 ) -> Result<BigUint, Box<dyn std::error::Error>> {
-    let id = rand::thread_rng().gen_range(1..=100);
+    let id = next_rpc_id();
 
     let payload = RpcRequest {
         jsonrpc: "2.0".to_string(),
@@ -311,13 +624,14 @@ async fn get_balance(
         id,
     };
 
-    let rpc = rpc_url.unwrap_or_else(|| "http://127.0.0.1:8545".to_string());
-
-    let client = Client::new();
-    let response = client.post(&rpc).json(&payload).send().await?;
-
-    let body = response.text().await?;
+    let body = post_rpc(rpc_url, &payload).await?;
     let balance_response: BalanceResponse = serde_json::from_str(&body)?;
+    verify_rpc_id(
+        &payload.method,
+        id,
+        balance_response.id,
+        balance_response.error.is_some(),
+    )?;
 
     // Surface the node's JSON-RPC error rather than a generic "missing field" serde error.
     if let Some(err) = balance_response.error {
@@ -334,14 +648,271 @@ async fn get_balance(
     Ok(balance)
 }
 
+// `jsonrpc` is part of the wire format but not inspected by the helpers.
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct RpcResponse {
+    jsonrpc: String,
+    id: Option<u64>,
+    #[serde(default)]
+    result: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+/// Issue an arbitrary JSON-RPC call and return its `result`. Like `get_balance`, a
+/// JSON-RPC error object is surfaced as an error carrying the node's message.
+async fn rpc_call(
+    rpc_url: Option<String>,
+    method: &str,
+    params: Vec<serde_json::Value>,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let id = next_rpc_id();
+
+    let payload = RpcRequest {
+        jsonrpc: "2.0".to_string(),
+        method: method.to_string(),
+        params,
+        id,
+    };
+
+    let body = post_rpc(rpc_url, &payload).await?;
+    let rpc_response: RpcResponse = serde_json::from_str(&body)?;
+    verify_rpc_id(method, id, rpc_response.id, rpc_response.error.is_some())?;
+
+    if let Some(err) = rpc_response.error {
+        return Err(format!("RPC error {}: {}", err.code, err.message).into());
+    }
+
+    rpc_response
+        .result
+        .ok_or_else(|| format!("{} response contained neither result nor error", method).into())
+}
+
+/// How long a provider that rejected a batch request gets sequential calls before
+/// batching is tried again.
+const BATCH_REJECTION_TTL: Duration = Duration::from_secs(600);
+
+/// Rejecting providers remembered at once; the oldest is forgotten first.
+const MAX_BATCH_REJECTIONS: usize = 256;
+
+/// RPC URLs whose provider answered a batch request with a JSON-RPC error object,
+/// e.g. because batching is disabled. They get sequential calls for
+/// `BATCH_REJECTION_TTL`. Shared by the HTTP handlers and the poller.
+#[derive(Default)]
+pub struct BatchRejections {
+    rejected: Mutex<HashMap<String, Instant>>,
+}
+
+impl BatchRejections {
+    fn contains(&self, rpc: &str) -> bool {
+        self.contains_at(rpc, Instant::now())
+    }
+
+    fn contains_at(&self, rpc: &str, now: Instant) -> bool {
+        let mut rejected = self.rejected.lock().unwrap();
+        match rejected.get(rpc) {
+            Some(at) if now.saturating_duration_since(*at) < BATCH_REJECTION_TTL => true,
+            Some(_) => {
+                rejected.remove(rpc);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&self, rpc: &str) {
+        let mut rejected = self.rejected.lock().unwrap();
+        if rejected.len() >= MAX_BATCH_REJECTIONS && !rejected.contains_key(rpc) {
+            let oldest = rejected
+                .iter()
+                .min_by_key(|(_, at)| **at)
+                .map(|(url, _)| url.clone());
+            if let Some(url) = oldest {
+                rejected.remove(&url);
+            }
+        }
+        rejected.insert(rpc.to_string(), Instant::now());
+    }
+}
+
+/// Issue several JSON-RPC calls as one batch request and return their `result`s in
+/// call order, failing on the first call that errors. A provider that answers a
+/// batch with a single JSON-RPC error object, whatever the HTTP status, is served by
+/// sequential `rpc_call`s instead. It is recorded in `rejections`, and skips the
+/// batch attempt from then on, only when the rejection is clear: a 2xx status or an
+/// error message about batches. Any other reply (a load balancer's 502 page) fails
+/// this call only.
+async fn rpc_batch(
+    rpc_url: Option<String>,
+    calls: Vec<(&str, Vec<serde_json::Value>)>,
+    rejections: &BatchRejections,
+) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
+    let rpc = rpc_url.unwrap_or_else(|| DEFAULT_RPC_URL.to_string());
+
+    if calls.len() > 1 && !rejections.contains(&rpc) {
+        let requests: Vec<RpcRequest> = calls
+            .iter()
+            .map(|(method, params)| RpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: method.to_string(),
+                params: params.clone(),
+                id: next_rpc_id(),
+            })
+            .collect();
+        let (status, body) = post_rpc_batch(&rpc, &requests).await?;
+        match serde_json::from_str::<serde_json::Value>(&body) {
+            Ok(responses @ serde_json::Value::Array(_)) => {
+                return demux_batch(&requests, serde_json::from_value(responses)?);
+            }
+            Ok(serde_json::Value::Object(reply)) if reply.get("error").is_some_and(|e| !e.is_null()) => {
+                let message = reply["error"]["message"].as_str().unwrap_or_default();
+                if status.is_success() || message.to_ascii_lowercase().contains("batch") {
+                    tracing::warn!(target = %telemetry::rpc_target(&rpc), http.status = status.as_u16(), "provider rejected batch request, using sequential calls");
+                    rejections.insert(&rpc);
+                } else {
+                    tracing::warn!(target = %telemetry::rpc_target(&rpc), http.status = status.as_u16(), "batch request failed, using sequential calls for this request");
+                }
+            }
+            _ => return Err(format!("batch request failed: HTTP {}", status).into()),
+        }
+    }
+
+    let mut results = Vec::with_capacity(calls.len());
+    for (method, params) in calls {
+        results.push(rpc_call(Some(rpc.clone()), method, params).await?);
+    }
+    Ok(results)
+}
+
+/// Match batch responses to their requests by `id`; responses may arrive in any order.
+fn demux_batch(
+    requests: &[RpcRequest],
+    responses: Vec<RpcResponse>,
+) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
+    let mut by_id: HashMap<u64, RpcResponse> = HashMap::new();
+    for response in responses {
+        let id = response.id.unwrap_or_default();
+        if !requests.iter().any(|r| r.id == id) {
+            return Err(Box::new(RpcIdMismatch {
+                method: "batch".to_string(),
+                expected: requests[0].id,
+                actual: Some(id),
+            }));
+        }
+        if by_id.insert(id, response).is_some() {
+            return Err(format!("malformed batch response: id {} answered twice", id).into());
+        }
+    }
+
+    requests
+        .iter()
+        .map(|request| {
+            let response = by_id.remove(&request.id).ok_or_else(|| RpcIdMismatch {
+                method: request.method.clone(),
+                expected: request.id,
+                actual: None,
+            })?;
+            if let Some(err) = response.error {
+                return Err(format!("RPC error {}: {}", err.code, err.message).into());
+            }
+            response.result.ok_or_else(|| {
+                format!(
+                    "{} response contained neither result nor error",
+                    request.method
+                )
+                .into()
+            })
+        })
+        .collect()
+}
+
+/// Parse a hex quantity (`0x`-prefixed) into a `u128`, enough for any wei-denominated fee.
+fn parse_hex_u128(s: &str) -> Result<u128, Box<dyn std::error::Error>> {
+    Ok(u128::from_str_radix(strip_hex_prefix(s), 16)
+        .map_err(|e| format!("Failed to parse hex quantity '{}': {}", s, e))?)
+}
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    use tiny_keccak::{Hasher, Keccak};
+
+    let mut hash = [0u8; 32];
+    let mut keccak = Keccak::v256();
+    keccak.update(data);
+    keccak.finalize(&mut hash);
+    hash
+}
+
+/// First four bytes of `keccak256(signature)`: the Solidity function selector.
+fn function_selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode an optionally `0x`-prefixed hex string into bytes.
+fn decode_hex(s: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let hex = strip_hex_prefix(s);
+    if !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("invalid hex data '{}'", s).into());
+    }
+    Ok((0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Call a contract function read-only via `eth_call` at `latest` and return the raw
+/// return data. `args` is the ABI-encoded argument data appended after the selector.
+async fn eth_call(
+    rpc_url: Option<String>,
+    to: &str,
+    signature: &str,
+    args: &str,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let args = decode_hex(args)?;
+    let data = format!(
+        "0x{}{}",
+        encode_hex(&function_selector(signature)),
+        encode_hex(&args)
+    );
+
+    let result = rpc_call(
+        rpc_url,
+        "eth_call",
+        vec![
+            serde_json::json!({ "to": to, "data": data }),
+            serde_json::Value::String("latest".to_string()),
+        ],
+    )
+    .await?;
+
+    let hex = result.as_str().ok_or("eth_call result is not a hex string")?;
+    decode_hex(hex)
+}
+
 /// HTTP handler for `/check_balance`. Validates the address and alert threshold,
 /// fetches the balance, and returns 200 when the balance is above the threshold or
 /// 500 (`balance_low`) when it is at or below it, so monitors can key off the status code.
+/// `warn` is an optional higher threshold reported as `balance_warning` with a 200;
+/// `damping` smooths the state across repeated probes of the same address.
+#[tracing::instrument(skip_all, fields(
+    target = %telemetry::rpc_target(rpc_url.as_deref().unwrap_or(DEFAULT_RPC_URL)),
+    chain.id = tracing::field::Empty,
+))]
 async fn check_balance(
     rpc_url: Option<String>,
     address: String,
     alert: Option<String>,
+    warn: Option<String>,
+    damping: hysteresis::Damping,
+    store: Arc<hysteresis::HysteresisStore>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
+    record_chain_id(&rpc_url).await;
+
     // Reject a missing or malformed address up front rather than forwarding it to
     // the RPC node and surfacing an opaque deserialization error.
     if !is_valid_eth_address(&address) {
@@ -350,6 +921,9 @@ async fn check_balance(
             balance: "0x0".to_string(),
             balance_decimal: "0".to_string(),
             alert_threshold: alert.clone().unwrap_or_else(|| "0".to_string()),
+            warn_threshold: warn.clone(),
+            raw_state: None,
+            state: None,
             status: "error: invalid or missing address (expected 0x + 40 hex digits)"
                 .to_string(),
         };
@@ -370,6 +944,9 @@ async fn check_balance(
                     balance: "0x0".to_string(),
                     balance_decimal: "0".to_string(),
                     alert_threshold: a.clone(),
+                    warn_threshold: warn.clone(),
+                    raw_state: None,
+                    state: None,
                     status: format!("error: invalid alert threshold '{}'", a),
                 };
                 return Ok(warp::reply::with_status(
@@ -381,7 +958,30 @@ async fn check_balance(
         None => BigUint::from(0u32),
     };
 
-    let balance = match get_balance(rpc_url, address.clone()).await {
+    let warn_threshold = match &warn {
+        Some(w) => match BigUint::from_str(w) {
+            Ok(t) => Some(t),
+            Err(_) => {
+                let error_response = CheckBalanceResponse {
+                    address: address.clone(),
+                    balance: "0x0".to_string(),
+                    balance_decimal: "0".to_string(),
+                    alert_threshold: alert_threshold.to_string(),
+                    warn_threshold: Some(w.clone()),
+                    raw_state: None,
+                    state: None,
+                    status: format!("error: invalid warn threshold '{}'", w),
+                };
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&error_response),
+                    warp::http::StatusCode::BAD_REQUEST,
+                ));
+            }
+        },
+        None => None,
+    };
+
+    let balance = match get_balance(rpc_url.clone(), address.clone()).await {
         Ok(bal) => bal,
         Err(e) => {
             let error_response = CheckBalanceResponse {
@@ -389,6 +989,9 @@ async fn check_balance(
                 balance: "0x0".to_string(),
                 balance_decimal: "0".to_string(),
                 alert_threshold: alert_threshold.to_string(),
+                warn_threshold: warn_threshold.as_ref().map(|w| w.to_string()),
+                raw_state: None,
+                state: None,
                 status: format!("error: {}", e),
             };
             return Ok(warp::reply::with_status(
@@ -398,21 +1001,38 @@ async fn check_balance(
         }
     };
 
+    let thresholds = hysteresis::Thresholds {
+        warn: warn_threshold.as_ref().map(|w| BigInt::from(w.clone())),
+        critical: BigInt::from(alert_threshold.clone()),
+        direction: hysteresis::Direction::AtOrBelow,
+        margin_pct: damping.margin_pct,
+    };
+    let key = format!(
+        "check_balance|{}|{}",
+        rpc_url.unwrap_or_default(),
+        address.to_lowercase()
+    );
+    let (raw, smoothed) = store.evaluate(&key, &BigInt::from(balance.clone()), &thresholds, damping);
+
     let response = CheckBalanceResponse {
         address: address.clone(),
         balance: format!("0x{:x}", balance),
         balance_decimal: balance.to_string(),
         alert_threshold: alert_threshold.to_string(),
-        status: if alert_threshold < balance {
-            "balance_sufficient".to_string()
-        } else {
-            "balance_low".to_string()
+        warn_threshold: warn_threshold.as_ref().map(|w| w.to_string()),
+        raw_state: Some(raw),
+        state: Some(smoothed),
+        status: match smoothed {
+            hysteresis::Level::Ok => "balance_sufficient".to_string(),
+            hysteresis::Level::Warning => "balance_warning".to_string(),
+            hysteresis::Level::Critical => "balance_low".to_string(),
         },
     };
 
-    // Must mirror the `status` field above: "balance_low" (alert_threshold >= balance)
-    // maps to an error status so monitors keying off the HTTP code alert at the threshold.
-    if alert_threshold >= balance {
+    // Must mirror the `status` field above: "balance_low" (smoothed state critical,
+    // i.e. at or below the alert threshold) maps to an error status so monitors keying
+    // off the HTTP code alert at the threshold.
+    if smoothed == hysteresis::Level::Critical {
         Ok(warp::reply::with_status(
             warp::reply::json(&response),
             warp::http::StatusCode::INTERNAL_SERVER_ERROR,
@@ -425,12 +1045,157 @@ async fn check_balance(
     }
 }
 
+/// Resolve on Ctrl-C or SIGTERM (sent by Kubernetes before killing the pod).
+async fn shutdown_signal() {
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+}
+
+/// What every request passes before reaching the routes: the per-client rate
+/// limit, then the client certificate and API key checks, then the response cache.
+struct Gate {
+    limiter: ratelimit::RateLimiter,
+    client_cert_required: bool,
+    api_keys: Option<auth::ApiKeys>,
+    ipc_allowlist: ipc::IpcAllowlist,
+    cache: cache::ResponseCache,
+}
+
+impl Gate {
+    async fn guard<S>(
+        self: Arc<Self>,
+        client_ip: std::net::IpAddr,
+        client_cert: bool,
+        service: S,
+        req: warp::http::Request<warp::hyper::Body>,
+    ) -> Result<warp::http::Response<warp::hyper::Body>, std::convert::Infallible>
+    where
+        S: warp::hyper::service::Service<
+            warp::http::Request<warp::hyper::Body>,
+            Response = warp::http::Response<warp::hyper::Body>,
+            Error = std::convert::Infallible,
+        >,
+    {
+        if let Err(retry_after) = self.limiter.check(client_ip, req.uri().path()) {
+            tracing::warn!(client = %client_ip, retry_after, "rate limited");
+            return Ok(ratelimit::too_many_requests(retry_after));
+        }
+        if self.client_cert_required && !client_cert {
+            if let Some(denied) = tls::require_client_cert(&req) {
+                tracing::warn!(client = %client_ip, "request without client certificate denied");
+                return Ok(denied);
+            }
+        }
+        if let Some(denied) = self.api_keys.as_ref().and_then(|keys| keys.deny(&req)) {
+            tracing::warn!(client = %client_ip, status = denied.status().as_u16(), "request denied");
+            return Ok(denied);
+        }
+        if let Some(denied) = self.ipc_allowlist.deny(&req) {
+            tracing::warn!(client = %client_ip, "request for an unlisted IPC socket denied");
+            return Ok(denied);
+        }
+        self.cache.serve(service, req).await
+    }
+}
+
 #[tokio::main]
 async fn main() {
+    telemetry::init();
+
     let port = env::var("PORT")
         .unwrap_or_else(|_| "9999".to_string())
         .parse::<u16>()
         .unwrap_or(9999);
+    let cache_ttl_secs = env::var("CACHE_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+    let rate_limit_per_minute = env::var("RATE_LIMIT_PER_MINUTE")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(0);
+
+    let config = match config::load_config() {
+        Ok(c) => Arc::new(c),
+        Err(e) => {
+            tracing::error!(error = %e, "invalid configuration");
+            std::process::exit(1);
+        }
+    };
+    let api_keys = match auth::ApiKeys::from_env() {
+        Ok(keys) => keys,
+        Err(e) => {
+            tracing::error!(error = %e, "invalid API keys");
+            std::process::exit(1);
+        }
+    };
+    let ipc_allowlist = ipc::IpcAllowlist::from_env(&config);
+    let tls_server = match tls::TlsSettings::from_env().and_then(|s| s.map(tls::TlsServer::new).transpose()) {
+        Ok(server) => server.map(Arc::new),
+        Err(e) => {
+            tracing::error!(error = %e, "invalid TLS configuration");
+            std::process::exit(1);
+        }
+    };
+    let monitor_state = Arc::new(state::MonitorState::default());
+
+    let history = match &config.history {
+        Some(h) => match history::History::open(&h.path) {
+            Ok(db) => Some(Arc::new(db)),
+            Err(e) => {
+                tracing::error!(error = %e, "failed to open history database");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let liveness = Arc::new(probes::Liveness::default());
+    liveness.spawn_heartbeat();
+
+    tracing::info!(targets = config.targets.len(), "configuration loaded");
+    let head_tracker = Arc::new(heads::HeadTracker::default());
+    // Providers that refuse batch requests, for the poller and the HTTP handlers.
+    let batch_rejections = Arc::new(BatchRejections::default());
+    for target in config.targets.iter().filter(|t| t.ws.is_some()) {
+        liveness.watch(
+            "head_subscription",
+            tokio::spawn(heads::run(target.clone(), head_tracker.clone())),
+        );
+    }
+    if !config.targets.is_empty() {
+        liveness.watch(
+            "poller",
+            tokio::spawn(poller::run(
+                config.clone(),
+                monitor_state.clone(),
+                history.clone(),
+                head_tracker.clone(),
+                batch_rejections.clone(),
+            )),
+        );
+    }
+
+    let with_config = warp::any().map(move || config.clone());
+    let with_state = warp::any().map(move || monitor_state.clone());
+    let with_history = warp::any().map(move || history.clone());
+    // Smoothing history for probes of the HTTP endpoints; the poller keeps its own.
+    let hysteresis_store = Arc::new(hysteresis::HysteresisStore::default());
+    let with_hysteresis = warp::any().map(move || hysteresis_store.clone());
+    let with_batch_rejections = {
+        let batch_rejections = batch_rejections.clone();
+        warp::any().map(move || batch_rejections.clone())
+    };
 
     let sync_route = warp::path::end()
         .and(warp::get())
@@ -441,35 +1206,455 @@ async fn main() {
         });
 
     let diff_route = warp::path("finalized_latest_diff")
+        .and(warp::get())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_hysteresis.clone())
+        .and(with_batch_rejections.clone())
+        .and_then(
+            |query_params: std::collections::HashMap<String, String>,
+             store: Arc<hysteresis::HysteresisStore>,
+             batch_rejections: Arc<BatchRejections>| {
+                let rpc_url = query_params.get("rpc").cloned();
+                let diff = query_params.get("diff").and_then(|d| d.parse::<i64>().ok());
+                let warn = query_params.get("warn").and_then(|d| d.parse::<i64>().ok());
+                let damping = hysteresis::Damping::from_query(&query_params);
+                finalized_latest_diff(rpc_url, diff, warn, damping, store, batch_rejections)
+            },
+        );
+
+    let balance_route = warp::path("check_balance")
+        .and(warp::get())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_hysteresis.clone())
+        .and_then(
+            |query_params: std::collections::HashMap<String, String>,
+             store: Arc<hysteresis::HysteresisStore>| {
+                let rpc_url = query_params.get("rpc").cloned();
+                let address = query_params.get("address").cloned().unwrap_or_default();
+                let alert = query_params.get("alert").cloned();
+                let warn = query_params.get("warn").cloned();
+                let damping = hysteresis::Damping::from_query(&query_params);
+                check_balance(rpc_url, address, alert, warn, damping, store)
+            },
+        );
+
+    let beacon_route = warp::path("beacon_status")
+        .and(warp::get())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and_then(|query_params: std::collections::HashMap<String, String>| {
+            let beacon_url = query_params.get("beacon").cloned();
+            let sync_distance = query_params
+                .get("sync_distance")
+                .and_then(|d| d.parse::<u64>().ok());
+            let finality_epochs = query_params
+                .get("finality_epochs")
+                .and_then(|d| d.parse::<u64>().ok());
+            let slots_per_epoch = query_params
+                .get("slots_per_epoch")
+                .and_then(|d| d.parse::<u64>().ok());
+            beacon::check_beacon(beacon_url, sync_distance, finality_epochs, slots_per_epoch)
+        });
+
+    let gas_route = warp::path("gas")
+        .and(warp::get())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_batch_rejections.clone())
+        .and_then(|query_params: std::collections::HashMap<String, String>, batch_rejections: Arc<BatchRejections>| {
+            let rpc_url = query_params.get("rpc").cloned();
+            let blocks = query_params.get("blocks").and_then(|b| b.parse::<u64>().ok());
+            let gwei = |key: &str| query_params.get(key).and_then(|v| v.parse::<f64>().ok());
+            let thresholds = gas::GasThresholds {
+                max_gas_price: gwei("max_gas_price"),
+                max_base_fee: gwei("max_base_fee"),
+                max_priority_fee: gwei("max_priority_fee"),
+                max_blob_fee: gwei("max_blob_fee"),
+            };
+            gas::check_gas(rpc_url, blocks, thresholds, batch_rejections)
+        });
+
+    let blob_route = warp::path("blob")
+        .and(warp::get())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_batch_rejections.clone())
+        .and_then(|query_params: std::collections::HashMap<String, String>, batch_rejections: Arc<BatchRejections>| {
+            let rpc_url = query_params.get("rpc").cloned();
+            let window = query_params.get("window").and_then(|w| w.parse::<u64>().ok());
+            let max_blob_fee = query_params
+                .get("max_blob_fee")
+                .and_then(|f| f.parse::<f64>().ok());
+            let update_fraction = query_params
+                .get("update_fraction")
+                .and_then(|f| f.parse::<u64>().ok());
+            blob::check_blobs(rpc_url, window, max_blob_fee, update_fraction, batch_rejections)
+        });
+
+    let txpool_route = warp::path("txpool")
         .and(warp::get())
         .and(warp::query::<std::collections::HashMap<String, String>>())
         .and_then(|query_params: std::collections::HashMap<String, String>| {
             let rpc_url = query_params.get("rpc").cloned();
-            let diff = query_params.get("diff").and_then(|d| d.parse::<i64>().ok());
-            finalized_latest_diff(rpc_url, diff)
+            let count = |key: &str| query_params.get(key).and_then(|v| v.parse::<u64>().ok());
+            let thresholds = txpool::TxpoolThresholds {
+                min_pending: count("min_pending"),
+                max_pending: count("max_pending"),
+                min_queued: count("min_queued"),
+                max_queued: count("max_queued"),
+            };
+            let senders = query_params.get("senders").is_some_and(|s| s == "true");
+            txpool::check_txpool(rpc_url, thresholds, senders)
         });
 
-    let balance_route = warp::path("check_balance")
-        .and(warp::get())
-        .and(warp::query::<std::collections::HashMap<String, String>>())
-        .and_then(|query_params: std::collections::HashMap<String, String>| {
-            let rpc_url = query_params.get("rpc").cloned();
-            let address = query_params.get("address").cloned().unwrap_or_default();
-            let alert = query_params.get("alert").cloned();
-            check_balance(rpc_url, address, alert)
-        });
+    let assertion_route = warp::path("assertion")
+        .and(warp::get())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_config.clone())
+        .and_then(
+            |query_params: std::collections::HashMap<String, String>, config: Arc<config::Config>| {
+                let target = query_params.get("target").cloned().unwrap_or_default();
+                let name = query_params.get("name").cloned().unwrap_or_default();
+                assertions::check_assertion(config, target, name)
+            },
+        );
+
+    let log_watcher_route = warp::path("log_watcher")
+        .and(warp::get())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_config.clone())
+        .and(with_state.clone())
+        .and_then(
+            |query_params: std::collections::HashMap<String, String>,
+             config: Arc<config::Config>,
+             state: Arc<state::MonitorState>| {
+                let target = query_params.get("target").cloned().unwrap_or_default();
+                let name = query_params.get("name").cloned().unwrap_or_default();
+                logs::check_log_watcher(config, state, target, name)
+            },
+        );
+
+    let livez_route = warp::path("livez")
+        .and(warp::get())
+        .and(warp::any().map(move || liveness.clone()))
+        .and_then(probes::livez);
+
+    let readyz_route = warp::path("readyz")
+        .and(warp::get())
+        .and(with_config.clone())
+        .and(with_state.clone())
+        .and_then(probes::readyz);
+
+    let health_route = warp::path("health")
+        .and(warp::get())
+        .and(with_config.clone())
+        .and(with_state.clone())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and_then(health::check_health);
+
+    let status_route = warp::path("status")
+        .and(warp::get())
+        .and(with_config.clone())
+        .and(with_state.clone())
+        .and_then(dashboard::status_page);
+
+    let heads_route = warp::path("heads")
+        .and(warp::get())
+        .and(warp::any().map(move || head_tracker.clone()))
+        .and_then(heads::get_heads);
+
+    let history_route = warp::path("history")
+        .and(warp::get())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_history.clone())
+        .and_then(
+            |query_params: std::collections::HashMap<String, String>,
+             history: Option<Arc<history::History>>| {
+                history::get_history(history, query_params)
+            },
+        );
+
+    let proposal_route = warp::path("proposal_lag")
+        .and(warp::get())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and_then(|query_params: std::collections::HashMap<String, String>| {
+            let l1_rpc = query_params.get("l1_rpc").cloned();
+            let l2_rpc = query_params.get("rpc").cloned();
+            let game_type = query_params
+                .get("game_type")
+                .and_then(|g| g.parse::<u32>().ok());
+            let source = match (query_params.get("oracle"), query_params.get("factory")) {
+                (Some(oracle), None) => Some(proposals::ProposalSource::OutputOracle(oracle.clone())),
+                (None, Some(factory)) => Some(proposals::ProposalSource::DisputeGameFactory(
+                    factory.clone(),
+                    game_type,
+                )),
+                _ => None,
+            };
+            let max_blocks = query_params
+                .get("max_blocks")
+                .and_then(|m| m.parse::<i64>().ok());
+            let max_seconds = query_params
+                .get("max_seconds")
+                .and_then(|m| m.parse::<u64>().ok());
+            proposals::check_proposal_lag(l1_rpc, l2_rpc, source, max_blocks, max_seconds)
+        });
+
+    let batch_route = warp::path("batch_lag")
+        .and(warp::get())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .and(with_batch_rejections.clone())
+        .and_then(|query_params: std::collections::HashMap<String, String>, batch_rejections: Arc<BatchRejections>| {
+            let rpc_url = query_params.get("rpc").cloned();
+            let batcher = query_params.get("batcher").cloned().unwrap_or_default();
+            let inbox = query_params.get("inbox").cloned().unwrap_or_default();
+            let max_seconds = query_params
+                .get("max_seconds")
+                .and_then(|m| m.parse::<u64>().ok());
+            let lookback = query_params
+                .get("lookback")
+                .and_then(|l| l.parse::<u64>().ok());
+            batches::check_batch_lag(rpc_url, batcher, inbox, max_seconds, lookback, batch_rejections)
+        });
+
+    let routes = sync_route
+        .or(diff_route)
+        .or(balance_route)
+        .or(beacon_route)
+        .or(gas_route)
+        .or(blob_route)
+        .or(txpool_route)
+        .or(assertion_route)
+        .or(log_watcher_route)
+        .or(proposal_route)
+        .or(batch_route)
+        .or(history_route)
+        .or(heads_route)
+        .or(status_route)
+        .or(health_route)
+        .or(livez_route)
+        .or(readyz_route);
+
+    tracing::info!(
+        port,
+        cache_ttl_secs,
+        rate_limit_per_minute,
+        auth = api_keys.is_some(),
+        tls = tls_server.is_some(),
+        mtls = tls_server.as_ref().is_some_and(|t| t.requires_client_cert()),
+        "starting server"
+    );
+
+    // Served through hyper directly so every request runs inside `telemetry::traced`.
+    let service = warp::service(routes);
+    let gate = Arc::new(Gate {
+        limiter: ratelimit::RateLimiter::new(rate_limit_per_minute),
+        client_cert_required: tls_server.as_ref().is_some_and(|t| t.requires_client_cert()),
+        api_keys,
+        ipc_allowlist,
+        cache: cache::ResponseCache::new(Duration::from_secs(cache_ttl_secs)),
+    });
+    let per_connection = move |client_ip: std::net::IpAddr, client_cert: bool| {
+        let (service, gate) = (service.clone(), gate.clone());
+        warp::hyper::service::service_fn(move |req| {
+            let (service, gate) = (service.clone(), gate.clone());
+            let guarded = warp::hyper::service::service_fn(move |req| {
+                gate.clone().guard(client_ip, client_cert, service.clone(), req)
+            });
+            telemetry::traced(guarded, req)
+        })
+    };
+    let addr = std::net::SocketAddr::from(([0, 0, 0, 0], port));
+    let result = match tls_server {
+        None => {
+            let make_service = warp::hyper::service::make_service_fn(
+                move |conn: &warp::hyper::server::conn::AddrStream| {
+                    let service = per_connection(conn.remote_addr().ip(), false);
+                    async move { Ok::<_, std::convert::Infallible>(service) }
+                },
+            );
+            warp::hyper::Server::bind(&addr)
+                .serve(make_service)
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+        }
+        Some(tls_server) => {
+            let listener = match tokio::net::TcpListener::bind(addr).await {
+                Ok(l) => l,
+                Err(e) => {
+                    tracing::error!(error = %e, "failed to bind");
+                    std::process::exit(1);
+                }
+            };
+            tokio::spawn(tls_server.clone().watch());
+            let make_service = warp::hyper::service::make_service_fn(
+                move |conn: &tokio_rustls::server::TlsStream<tokio::net::TcpStream>| {
+                    let service = per_connection(tls::peer_ip(conn), tls::has_client_cert(conn));
+                    async move { Ok::<_, std::convert::Infallible>(service) }
+                },
+            );
+            warp::hyper::Server::builder(tls_server.incoming(listener))
+                .serve(make_service)
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+        }
+    };
+    if let Err(e) = result {
+        tracing::error!(error = %e, "server error");
+    }
+    tracing::info!("shutting down");
+    // Flushing blocks until queued spans are exported.
+    let _ = tokio::task::spawn_blocking(telemetry::shutdown).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+
+    /// Everything logged on this thread, with span fields, while the guard lives.
+    pub(crate) fn capture_logs() -> (tracing::subscriber::DefaultGuard, Arc<Mutex<Vec<u8>>>) {
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_max_level(tracing::Level::TRACE)
+            .with_writer(move || Buffer(writer.clone()))
+            .finish();
+        (tracing::subscriber::set_default(subscriber), logs)
+    }
+
+    /// Mock body for a JSON-RPC batch: one response per request carrying its id,
+    /// with `results` in request order. The array is reversed, since providers may
+    /// answer a batch in any order.
+    pub(crate) fn batch_reply(
+        results: Vec<serde_json::Value>,
+    ) -> impl Fn(&mockito::Request) -> Vec<u8> + Send + Sync + 'static {
+        move |request| {
+            let requests: Vec<serde_json::Value> =
+                serde_json::from_slice(request.body().unwrap()).unwrap();
+            let mut responses: Vec<serde_json::Value> = requests
+                .iter()
+                .zip(&results)
+                .map(|(req, result)| {
+                    serde_json::json!({"jsonrpc": "2.0", "id": req["id"], "result": result})
+                })
+                .collect();
+            responses.reverse();
+            serde_json::to_vec(&responses).unwrap()
+        }
+    }
+
+    /// A path in the temp directory, unique to this test process and `name`, that
+    /// is removed, file or directory, when the test drops it.
+    pub(crate) struct TempPath(std::path::PathBuf);
+
+    impl TempPath {
+        pub(crate) fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "monitor-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let temp = TempPath(path);
+            temp.remove();
+            temp
+        }
+
+        pub(crate) fn as_str(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+
+        fn remove(&self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
 
-    let routes = sync_route.or(diff_route).or(balance_route);
+    impl std::ops::Deref for TempPath {
+        type Target = std::path::Path;
 
-    println!("Starting server on port {}", port);
+        fn deref(&self) -> &std::path::Path {
+            &self.0
+        }
+    }
 
-    warp::serve(routes).run(([0, 0, 0, 0], port)).await;
-}
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            self.remove();
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use mockito::Server;
+    /// Mock body for a JSON-RPC node that answers single and batch requests alike:
+    /// `respond` gives the response to each request, which is sent with its id.
+    pub(crate) fn rpc_node(
+        respond: impl Fn(&serde_json::Value) -> String + Send + Sync + 'static,
+    ) -> impl Fn(&mockito::Request) -> Vec<u8> + Send + Sync + 'static {
+        move |request| {
+            let body: serde_json::Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+            let answer = |req: &serde_json::Value| {
+                let mut response: serde_json::Value =
+                    serde_json::from_str(&respond(req)).unwrap();
+                response["id"] = req["id"].clone();
+                response
+            };
+            let reply = match &body {
+                serde_json::Value::Array(requests) => requests.iter().map(answer).collect(),
+                request => answer(request),
+            };
+            reply.to_string().into_bytes()
+        }
+    }
+
+    /// Mock body for a JSON-RPC endpoint: `body` with its `id` replaced by the id of
+    /// the request being answered, as a real node would. A null `id` is kept.
+    pub(crate) fn echo_rpc_id(
+        body: impl Into<String>,
+    ) -> impl Fn(&mockito::Request) -> Vec<u8> + Send + Sync + 'static {
+        let body = body.into();
+        move |request| {
+            let id = request
+                .body()
+                .ok()
+                .and_then(|b| serde_json::from_slice::<serde_json::Value>(b).ok())
+                .and_then(|r| r.get("id").cloned());
+            let mut response = match serde_json::from_str::<serde_json::Value>(&body) {
+                Ok(r) => r,
+                Err(_) => return body.clone().into_bytes(),
+            };
+            if let (Some(id), Some(fields)) = (id, response.as_object_mut()) {
+                if fields.get("id").is_some_and(|i| !i.is_null()) {
+                    fields.insert("id".to_string(), id);
+                }
+            }
+            response.to_string().into_bytes()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transport_error_does_not_leak_the_rpc_url() {
+        let (_guard, logs) = capture_logs();
+        let err = rpc_call(
+            Some("http://127.0.0.1:1/v2/SECRET?key=SECRET".to_string()),
+            "eth_blockNumber",
+            vec![],
+        )
+        .await
+        .unwrap_err();
+        assert!(!err.to_string().contains("SECRET"), "{}", err);
+        let logs = String::from_utf8(logs.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("transport_error"), "{}", logs);
+        assert!(!logs.contains("SECRET"), "{}", logs);
+    }
 
     #[tokio::test]
     async fn test_get_block_number_success() {
@@ -478,7 +1663,7 @@ mod tests {
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":"0x1a2b3c"}"#)
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":"0x1a2b3c"}"#))
             .create_async()
             .await;
 
@@ -505,25 +1690,25 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_get_block_by_tag_success() {
+    async fn test_get_block_header_by_tag_success() {
         let mut server = Server::new_async().await;
         let mock = server
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0xabcdef"}}"#)
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0xabcdef"}}"#))
             .create_async()
             .await;
 
-        let result = get_block_by_tag(Some(server.url()), "latest").await;
+        let result = get_block_header_by_tag(Some(server.url()), "latest").await;
         mock.assert_async().await;
 
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 0xabcdef);
+        assert_eq!(result.unwrap().number, 0xabcdef);
     }
 
     #[tokio::test]
-    async fn test_get_block_by_tag_error() {
+    async fn test_get_block_header_by_tag_error() {
         let mut server = Server::new_async().await;
         let mock = server
             .mock("POST", "/")
@@ -531,12 +1716,33 @@ mod tests {
             .create_async()
             .await;
 
-        let result = get_block_by_tag(Some(server.url()), "latest").await;
+        let result = get_block_header_by_tag(Some(server.url()), "latest").await;
         mock.assert_async().await;
 
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_get_block_header_by_tag_reads_fork_fields() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(
+                r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0x10","timestamp":"0x6553f100","baseFeePerGas":"0x3b9aca00","blobGasUsed":"0x60000","excessBlobGas":"0x0"}}"#,
+            ))
+            .create_async()
+            .await;
+
+        let header = get_block_header_by_tag(Some(server.url()), "latest")
+            .await
+            .unwrap();
+        assert_eq!(header.number, 16);
+        assert_eq!(header.blob_gas_used, Some(0x60000));
+        assert_eq!(header.excess_blob_gas, Some(0));
+    }
+
     #[tokio::test]
     async fn test_get_balance_success() {
         let mut server = Server::new_async().await;
@@ -544,7 +1750,7 @@ mod tests {
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":"0xde0b6b3a7640000"}"#)
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":"0xde0b6b3a7640000"}"#))
             .create_async()
             .await;
 
@@ -588,7 +1794,7 @@ mod tests {
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(body)
+            .with_body_from_request(echo_rpc_id(body))
             .create_async()
             .await;
 
@@ -596,6 +1802,9 @@ mod tests {
             Some(server.url()),
             VALID_ADDRESS.to_string(),
             alert.map(|a| a.to_string()),
+            None,
+            Default::default(),
+            Default::default(),
         )
         .await
         .unwrap()
@@ -648,10 +1857,336 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_check_balance_warn_threshold_returns_ok_with_warning() {
+        use warp::Reply;
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(ONE_ETH_HEX_BODY))
+            .create_async()
+            .await;
+
+        let response = check_balance(
+            Some(server.url()),
+            VALID_ADDRESS.to_string(),
+            Some("1".to_string()),
+            Some("2000000000000000000".to_string()),
+            Default::default(),
+            Default::default(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["status"], "balance_warning");
+        assert_eq!(json["state"], "warning");
+    }
+
+    #[tokio::test]
+    async fn test_finalized_latest_diff_reads_both_heads_in_one_batch() {
+        use warp::Reply;
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex(r"^\[".to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(batch_reply(vec![
+                serde_json::json!({"number": "0x64"}),
+                serde_json::json!({"number": "0x70"}),
+            ]))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let response = finalized_latest_diff(
+            Some(server.url()),
+            Some(64),
+            None,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        mock.assert_async().await;
+
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["finalized_block"], 100);
+        assert_eq!(json["latest_block"], 112);
+        assert_eq!(json["difference"], 12);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_batch_falls_back_to_sequential_calls_when_rejected() {
+        let mut server = Server::new_async().await;
+        let batch = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex(r"^\[".to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32600,"message":"batch requests are not supported"}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        for (method, result) in [("eth_chainId", "0x1"), ("net_version", "1")] {
+            server
+                .mock("POST", "/")
+                .match_body(mockito::Matcher::PartialJsonString(format!(
+                    r#"{{"method":"{}"}}"#,
+                    method
+                )))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body_from_request(echo_rpc_id(format!(
+                    r#"{{"jsonrpc":"2.0","id":1,"result":"{}"}}"#,
+                    result
+                )))
+                .create_async()
+                .await;
+        }
+
+        let rejections = BatchRejections::default();
+        for _ in 0..2 {
+            let results = rpc_batch(
+                Some(server.url()),
+                vec![("eth_chainId", vec![]), ("net_version", vec![])],
+                &rejections,
+            )
+            .await
+            .unwrap();
+            assert_eq!(results, vec![serde_json::json!("0x1"), serde_json::json!("1")]);
+        }
+        // The rejection is remembered: the second round went straight to single calls.
+        batch.assert_async().await;
+        assert!(rejections.contains(&server.url()));
+    }
+
+    #[tokio::test]
+    async fn test_rpc_batch_error_object_with_error_status_falls_back() {
+        for (status, message, remembered) in [
+            (429, "rate limit exceeded", false),
+            (400, "Batch requests are disabled on this plan", true),
+        ] {
+            let mut server = Server::new_async().await;
+            server
+                .mock("POST", "/")
+                .match_body(mockito::Matcher::Regex(r"^\[".to_string()))
+                .with_status(status)
+                .with_header("content-type", "application/json")
+                .with_body(format!(
+                    r#"{{"jsonrpc":"2.0","id":null,"error":{{"code":-32600,"message":"{}"}}}}"#,
+                    message
+                ))
+                .create_async()
+                .await;
+            server
+                .mock("POST", "/")
+                .match_body(mockito::Matcher::Regex(r"^\{".to_string()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#))
+                .create_async()
+                .await;
+
+            let rejections = BatchRejections::default();
+            let results = rpc_batch(
+                Some(server.url()),
+                vec![("eth_chainId", vec![]), ("net_version", vec![])],
+                &rejections,
+            )
+            .await
+            .unwrap();
+            assert_eq!(results, vec![serde_json::json!("0x1"), serde_json::json!("0x1")]);
+            assert_eq!(rejections.contains(&server.url()), remembered, "{}", message);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpc_batch_transient_failure_is_not_remembered() {
+        let mut server = Server::new_async().await;
+        let bad_gateway = server
+            .mock("POST", "/")
+            .with_status(502)
+            .with_header("content-type", "text/html")
+            .with_body("<html>502 Bad Gateway</html>")
+            .expect(1)
+            .create_async()
+            .await;
+        let rejections = BatchRejections::default();
+        let calls = || vec![("eth_chainId", vec![]), ("net_version", vec![])];
+
+        let err = rpc_batch(Some(server.url()), calls(), &rejections)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("502"), "{}", err);
+        assert!(!rejections.contains(&server.url()));
+        bad_gateway.assert_async().await;
+        bad_gateway.remove_async().await;
+
+        let batch = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex(r"^\[".to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(batch_reply(vec![
+                serde_json::json!("0x1"),
+                serde_json::json!("1"),
+            ]))
+            .expect(1)
+            .create_async()
+            .await;
+        let results = rpc_batch(Some(server.url()), calls(), &rejections)
+            .await
+            .unwrap();
+        assert_eq!(results, vec![serde_json::json!("0x1"), serde_json::json!("1")]);
+        batch.assert_async().await;
+    }
+
+    #[test]
+    fn test_chain_ids_retry_failures_and_are_capped() {
+        let mut ids = ChainIds::default();
+        let now = Instant::now();
+        ids.insert("http://up".to_string(), Some(10), now);
+        ids.insert("http://down".to_string(), None, now);
+        assert_eq!(ids.get("http://up", now + CHAIN_ID_RETRY), Some(Some(10)));
+        // A failure is remembered for a while, then looked up again.
+        assert_eq!(ids.get("http://down", now), Some(None));
+        assert_eq!(ids.get("http://down", now + CHAIN_ID_RETRY), None);
+        assert_eq!(ids.get("http://unknown", now), None);
+
+        for i in 0..MAX_CHAIN_IDS {
+            ids.insert(format!("http://{}", i), Some(1), now + Duration::from_secs(1));
+        }
+        assert_eq!(ids.ids.len(), MAX_CHAIN_IDS);
+        assert_eq!(ids.get("http://up", now), None);
+    }
+
+    #[test]
+    fn test_batch_rejections_expire_and_are_capped() {
+        let rejections = BatchRejections::default();
+        rejections.insert("http://a");
+        let now = Instant::now();
+        assert!(rejections.contains_at("http://a", now));
+        assert!(!rejections.contains_at("http://a", now + BATCH_REJECTION_TTL));
+        // Expired entries are dropped when looked up.
+        assert!(!rejections.contains_at("http://a", now));
+
+        for i in 0..MAX_BATCH_REJECTIONS + 10 {
+            rejections.insert(&format!("http://{}", i));
+        }
+        assert_eq!(rejections.rejected.lock().unwrap().len(), MAX_BATCH_REJECTIONS);
+        assert!(rejections.contains(&format!("http://{}", MAX_BATCH_REJECTIONS + 9)));
+    }
+
+    #[test]
+    fn test_demux_batch_matches_by_id_and_rejects_unknown_ids() {
+        let request = |method: &str, id| RpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params: vec![],
+            id,
+        };
+        let requests = vec![request("eth_chainId", 7), request("eth_blockNumber", 8)];
+        let responses = |body: &str| serde_json::from_str::<Vec<RpcResponse>>(body).unwrap();
+
+        let results = demux_batch(
+            &requests,
+            responses(r#"[{"jsonrpc":"2.0","id":8,"result":"0x10"},{"jsonrpc":"2.0","id":7,"result":"0x1"}]"#),
+        )
+        .unwrap();
+        assert_eq!(results, vec![serde_json::json!("0x1"), serde_json::json!("0x10")]);
+
+        let err = demux_batch(
+            &requests,
+            responses(r#"[{"jsonrpc":"2.0","id":7,"result":"0x1"},{"jsonrpc":"2.0","id":9,"result":"0x10"}]"#),
+        )
+        .unwrap_err();
+        assert!(err.downcast_ref::<RpcIdMismatch>().is_some());
+
+        let err = demux_batch(
+            &requests,
+            responses(r#"[{"jsonrpc":"2.0","id":7,"result":"0x1"},{"jsonrpc":"2.0","id":8,"error":{"code":-32000,"message":"header not found"}}]"#),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("header not found"));
+
+        let err = demux_batch(
+            &requests,
+            responses(r#"[{"jsonrpc":"2.0","id":7,"result":"0x1"},{"jsonrpc":"2.0","id":7,"result":"0x2"},{"jsonrpc":"2.0","id":8,"result":"0x10"}]"#),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("id 7 answered twice"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_check_balance_fail_after_smooths_repeated_probes() {
+        use warp::Reply;
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(ONE_ETH_HEX_BODY))
+            .create_async()
+            .await;
+        let store = Arc::new(hysteresis::HysteresisStore::default());
+        let damping = hysteresis::Damping {
+            fail_after: 2,
+            ..Default::default()
+        };
+
+        let mut probes = Vec::new();
+        for _ in 0..2 {
+            let response = check_balance(
+                Some(server.url()),
+                VALID_ADDRESS.to_string(),
+                Some("2000000000000000000".to_string()),
+                None,
+                damping,
+                store.clone(),
+            )
+            .await
+            .unwrap()
+            .into_response();
+            let status = response.status();
+            let body = warp::hyper::body::to_bytes(response.into_body())
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            probes.push((status, json));
+        }
+
+        assert_eq!(probes[0].0, warp::http::StatusCode::OK);
+        assert_eq!(probes[0].1["raw_state"], "critical");
+        assert_eq!(probes[0].1["state"], "ok");
+        assert_eq!(probes[1].0, warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(probes[1].1["state"], "critical");
+    }
+
     #[tokio::test]
     async fn test_check_balance_invalid_address_returns_bad_request() {
         use warp::Reply;
-        let status = check_balance(None, "not-an-address".to_string(), None)
+        let status = check_balance(
+            None,
+            "not-an-address".to_string(),
+            None,
+            None,
+            Default::default(),
+            Default::default(),
+        )
             .await
             .unwrap()
             .into_response()
@@ -666,6 +2201,9 @@ mod tests {
             None,
             VALID_ADDRESS.to_string(),
             Some("not-a-number".to_string()),
+            None,
+            Default::default(),
+            Default::default(),
         )
         .await
         .unwrap()
@@ -718,7 +2256,7 @@ mod tests {
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"parse error"}}"#)
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"parse error"}}"#))
             .create_async()
             .await;
 
@@ -729,6 +2267,70 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("parse error"));
     }
 
+    #[tokio::test]
+    async fn test_rpc_call_forwards_request_id() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .match_header(telemetry::REQUEST_ID_HEADER, "req-42")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#))
+            .create_async()
+            .await;
+
+        let result = telemetry::with_request_id(
+            "req-42".to_string(),
+            rpc_call(Some(server.url()), "eth_chainId", vec![]),
+        )
+        .await;
+        mock.assert_async().await;
+
+        assert_eq!(result.unwrap(), serde_json::json!("0x1"));
+    }
+
+    #[test]
+    fn test_next_rpc_id_is_strictly_increasing() {
+        let first = next_rpc_id();
+        let second = next_rpc_id();
+        assert!(second > first);
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_response_id_is_distinct_error() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc":"2.0","id":0,"result":"0x10"}"#)
+            .expect_at_least(3)
+            .create_async()
+            .await;
+
+        let err = get_block_number(Some(server.url())).await.unwrap_err();
+        assert!(err.downcast_ref::<RpcIdMismatch>().is_some());
+        assert!(err.to_string().contains("received 0"));
+
+        let err = get_balance(Some(server.url()), "0x1".to_string())
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<RpcIdMismatch>().is_some());
+
+        let err = get_block_header_by_tag(Some(server.url()), "latest")
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<RpcIdMismatch>().is_some());
+    }
+
+    #[test]
+    fn test_verify_rpc_id_allows_null_only_on_errors() {
+        assert!(verify_rpc_id("eth_call", 7, Some(7), false).is_ok());
+        assert!(verify_rpc_id("eth_call", 7, None, true).is_ok());
+        assert!(verify_rpc_id("eth_call", 7, None, false).is_err());
+        assert!(verify_rpc_id("eth_call", 7, Some(8), true).is_err());
+    }
+
     #[tokio::test]
     async fn test_get_balance_rpc_error_surfaced() {
         let mut server = Server::new_async().await;
@@ -736,9 +2338,9 @@ mod tests {
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(
+            .with_body_from_request(echo_rpc_id(
                 r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"invalid address"}}"#,
-            )
+            ))
             .create_async()
             .await;
 
@@ -780,6 +2382,9 @@ mod tests {
             balance: "0x204fce5e3e25026110000000".to_string(),
             balance_decimal: "99999999999999999999999999999999999999".to_string(),
             alert_threshold: "1000000000000000000".to_string(),
+            warn_threshold: None,
+            raw_state: Some(hysteresis::Level::Ok),
+            state: Some(hysteresis::Level::Ok),
             status: "balance_sufficient".to_string(),
         };
 
@@ -834,7 +2439,7 @@ mod tests {
     #[test]
     fn test_is_valid_eth_address_valid_mixed_case_checksum() {
         assert!(is_valid_eth_address(
-            "0xAbC1230000000000000000000000000000000a"
+            "0xAbC123000000000000000000000000000000000a"
         ));
     }
 
@@ -925,9 +2530,9 @@ mod tests {
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(
+            .with_body_from_request(echo_rpc_id(
                 r#"{"jsonrpc":"2.0","id":1,"result":"0x1","error":{"code":-32000,"message":"conflict"}}"#,
-            )
+            ))
             .create_async()
             .await;
 
@@ -945,7 +2550,7 @@ mod tests {
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(r#"{"jsonrpc":"2.0","id":1}"#)
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1}"#))
             .create_async()
             .await;
 
@@ -968,7 +2573,7 @@ mod tests {
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":""}"#)
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":""}"#))
             .create_async()
             .await;
 
@@ -991,7 +2596,7 @@ mod tests {
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(r#"{"jsonrpc":"2.0","id":1,"result":"5"}"#)
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":"5"}"#))
             .create_async()
             .await;
 
@@ -1002,6 +2607,86 @@ mod tests {
         assert_eq!(result.unwrap(), 5);
     }
 
+    #[tokio::test]
+    async fn test_rpc_call_returns_result() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":{"pending":"0x1"}}"#))
+            .create_async()
+            .await;
+
+        let result = rpc_call(Some(server.url()), "txpool_status", vec![]).await;
+        mock.assert_async().await;
+
+        assert_eq!(result.unwrap()["pending"], "0x1");
+    }
+
+    #[tokio::test]
+    async fn test_rpc_call_surfaces_rpc_error() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"method not found"}}"#))
+            .create_async()
+            .await;
+
+        let result = rpc_call(Some(server.url()), "eth_gasPrice", vec![]).await;
+        assert!(result.unwrap_err().to_string().contains("method not found"));
+    }
+
+    #[test]
+    fn test_parse_hex_u128() {
+        assert_eq!(parse_hex_u128("0x3b9aca00").unwrap(), 1_000_000_000);
+        assert!(parse_hex_u128("0xzz").is_err());
+    }
+
+    #[test]
+    fn test_function_selector() {
+        assert_eq!(encode_hex(&function_selector("transfer(address,uint256)")), "a9059cbb");
+        assert_eq!(encode_hex(&function_selector("paused()")), "5c975abb");
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("0x00ff").unwrap(), vec![0x00, 0xff]);
+        assert_eq!(decode_hex("").unwrap(), Vec::<u8>::new());
+        assert!(decode_hex("0x0").is_err());
+        assert!(decode_hex("0xzz").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_eth_call_sends_selector_and_args() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"method":"eth_call","params":[{"to":"0x0000000000000000000000000000000000000001","data":"0x70a08231000000000000000000000000000000000000000000000000000000000000beef"},"latest"]}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":"0x0000000000000000000000000000000000000000000000000000000000000001"}"#))
+            .create_async()
+            .await;
+
+        let result = eth_call(
+            Some(server.url()),
+            VALID_ADDRESS,
+            "balanceOf(address)",
+            "0x000000000000000000000000000000000000000000000000000000000000beef",
+        )
+        .await;
+        mock.assert_async().await;
+
+        let data = result.unwrap();
+        assert_eq!(data.len(), 32);
+        assert_eq!(data[31], 1);
+    }
+
     #[tokio::test]
     async fn test_check_balance_accepts_mixed_case_address() {
         use warp::Reply;
@@ -1010,14 +2695,17 @@ mod tests {
             .mock("POST", "/")
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body(ONE_ETH_HEX_BODY)
+            .with_body_from_request(echo_rpc_id(ONE_ETH_HEX_BODY))
             .create_async()
             .await;
 
         let status = check_balance(
             Some(server.url()),
-            "0xAbC1230000000000000000000000000000000a".to_string(),
+            "0xAbC123000000000000000000000000000000000a".to_string(),
             Some("1".to_string()),
+            None,
+            Default::default(),
+            Default::default(),
         )
         .await
         .unwrap()
@@ -1030,7 +2718,14 @@ mod tests {
     #[tokio::test]
     async fn test_check_balance_missing_address_returns_bad_request() {
         use warp::Reply;
-        let status = check_balance(None, "".to_string(), None)
+        let status = check_balance(
+            None,
+            "".to_string(),
+            None,
+            None,
+            Default::default(),
+            Default::default(),
+        )
             .await
             .unwrap()
             .into_response()
@@ -0,0 +1,540 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::time::Duration;
+
+use crate::config::{Notifier, NotifierFormat};
+use crate::state::CheckResult;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlertEvent {
+    Firing,
+    Resolved,
+}
+
+impl AlertEvent {
+    fn as_str(self) -> &'static str {
+        match self {
+            AlertEvent::Firing => "firing",
+            AlertEvent::Resolved => "resolved",
+        }
+    }
+}
+
+struct AlertState {
+    healthy: bool,
+    last_notified: u64,
+}
+
+/// Tracks the last known health of every (target, check) so that only transitions,
+/// and still-firing alerts past the re-notify interval, are sent.
+pub struct AlertTracker {
+    renotify_secs: u64,
+    states: HashMap<(String, String), AlertState>,
+}
+
+impl AlertTracker {
+    pub fn new(renotify_secs: u64) -> Self {
+        AlertTracker {
+            renotify_secs,
+            states: HashMap::new(),
+        }
+    }
+
+    /// The notification `result` calls for, if any, without changing state. A check
+    /// first seen healthy is silent; first seen unhealthy fires.
+    pub fn decide(&self, result: &CheckResult) -> Option<AlertEvent> {
+        let key = (result.target.clone(), result.check.clone());
+        let state = match self.states.get(&key) {
+            Some(s) => s,
+            None => return (!result.healthy).then_some(AlertEvent::Firing),
+        };
+
+        if state.healthy != result.healthy {
+            return Some(if result.healthy {
+                AlertEvent::Resolved
+            } else {
+                AlertEvent::Firing
+            });
+        }
+
+        let renotify_due = self.renotify_secs > 0
+            && result.checked_at.saturating_sub(state.last_notified) >= self.renotify_secs;
+        (!state.healthy && renotify_due).then_some(AlertEvent::Firing)
+    }
+
+    /// Record that the notification for `result` went out. Until then `decide` keeps
+    /// asking for it, so a failed delivery is retried on the next round.
+    pub fn delivered(&mut self, result: &CheckResult) {
+        self.states.insert(
+            (result.target.clone(), result.check.clone()),
+            AlertState {
+                healthy: result.healthy,
+                last_notified: result.checked_at,
+            },
+        );
+    }
+
+    /// `decide`, recording the notification as delivered straight away.
+    #[cfg(test)]
+    fn observe(&mut self, result: &CheckResult) -> Option<AlertEvent> {
+        let event = self.decide(result);
+        if event.is_some() {
+            self.delivered(result);
+        }
+        event
+    }
+}
+
+fn summary(event: AlertEvent, result: &CheckResult) -> String {
+    format!(
+        "[{}] {}/{}: {}",
+        event.as_str().to_uppercase(),
+        result.target,
+        result.check,
+        result.status
+    )
+}
+
+/// Incident key shared by the trigger and resolve events of one (target, check), so
+/// the incident service groups repeats and closes the right incident on recovery.
+fn dedup_key(result: &CheckResult) -> String {
+    format!("monitor/{}/{}", result.target, result.check)
+}
+
+/// Request body for `event` in the notifier's wire format.
+fn payload(config: &Notifier, event: AlertEvent, result: &CheckResult) -> Value {
+    match config.format {
+        NotifierFormat::Json => json!({
+            "event": event.as_str(),
+            "target": result.target,
+            "check": result.check,
+            "healthy": result.healthy,
+            "status": result.status,
+            "detail": result.detail,
+            "checked_at": result.checked_at,
+        }),
+        NotifierFormat::Slack => json!({ "text": summary(event, result) }),
+        NotifierFormat::Discord => json!({ "content": summary(event, result) }),
+        NotifierFormat::PagerDuty => match event {
+            AlertEvent::Firing => json!({
+                "routing_key": config.routing_key,
+                "event_action": "trigger",
+                "dedup_key": dedup_key(result),
+                "payload": {
+                    "summary": summary(event, result),
+                    "source": result.target,
+                    "severity": config.severity,
+                    "component": result.check,
+                    "custom_details": {
+                        "status": result.status,
+                        "detail": result.detail,
+                        "checked_at": result.checked_at,
+                    },
+                },
+            }),
+            AlertEvent::Resolved => json!({
+                "routing_key": config.routing_key,
+                "event_action": "resolve",
+                "dedup_key": dedup_key(result),
+            }),
+        },
+        NotifierFormat::Opsgenie => match event {
+            AlertEvent::Firing => json!({
+                "message": summary(event, result),
+                "alias": dedup_key(result),
+                "source": "monitor",
+                "entity": result.target,
+                "details": {
+                    "target": result.target,
+                    "check": result.check,
+                    "status": result.status,
+                },
+            }),
+            AlertEvent::Resolved => json!({ "source": "monitor" }),
+        },
+    }
+}
+
+/// Where to POST `event`. Opsgenie closes alerts through a per-alias endpoint; the
+/// alias is percent-encoded as a single path segment, since target and check names
+/// may contain `/`, `?`, `#` or spaces.
+fn endpoint(config: &Notifier, event: AlertEvent, result: &CheckResult) -> String {
+    match (config.format, event) {
+        (NotifierFormat::Opsgenie, AlertEvent::Resolved) => {
+            let mut url = match reqwest::Url::parse(&config.url) {
+                Ok(url) => url,
+                Err(_) => return config.url.clone(),
+            };
+            if let Ok(mut segments) = url.path_segments_mut() {
+                segments
+                    .pop_if_empty()
+                    .push(&dedup_key(result))
+                    .push("close");
+            }
+            url.set_query(Some("identifierType=alias"));
+            url.to_string()
+        }
+        _ => config.url.clone(),
+    }
+}
+
+/// A configured webhook together with its de-duplication state.
+pub struct WebhookNotifier {
+    config: Notifier,
+    tracker: AlertTracker,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: Notifier) -> Self {
+        WebhookNotifier {
+            tracker: AlertTracker::new(config.renotify_minutes * 60),
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Feed a fresh check result; posts to the webhook when the tracker says so.
+    /// The transition only counts as sent after a 2xx, so a failed delivery is
+    /// retried with the next result.
+    pub async fn process(&mut self, result: &CheckResult) {
+        if !self.config.wants(&result.check) {
+            return;
+        }
+        let event = match self.tracker.decide(result) {
+            Some(e) => e,
+            None => return,
+        };
+
+        let url = endpoint(&self.config, event, result);
+        let mut request = self
+            .client
+            .post(&url)
+            .timeout(Duration::from_secs(10))
+            .json(&payload(&self.config, event, result));
+        if let (NotifierFormat::Opsgenie, Some(key)) = (self.config.format, &self.config.api_key) {
+            request = request.header("Authorization", format!("GenieKey {}", key));
+        }
+        let outcome = request.send().await.and_then(|r| r.error_for_status());
+
+        match outcome {
+            Ok(_) => {
+                self.tracker.delivered(result);
+                tracing::info!(
+                    target = %result.target,
+                    check = %result.check,
+                    format = ?self.config.format,
+                    "sent {} notification",
+                    summary(event, result)
+                )
+            }
+            Err(e) => tracing::warn!(
+                target = %result.target,
+                check = %result.check,
+                format = ?self.config.format,
+                // Webhook URLs are credentials (Slack, Discord), so drop them.
+                error = %e.without_url(),
+                "notification failed"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    fn result(healthy: bool, checked_at: u64) -> CheckResult {
+        CheckResult {
+            target: "l1".to_string(),
+            check: "sync".to_string(),
+            healthy,
+            status: if healthy { "synced" } else { "not_synced" }.to_string(),
+            detail: Value::Null,
+            checked_at,
+            latency_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_tracker_notifies_on_transitions_only() {
+        let mut tracker = AlertTracker::new(0);
+        assert_eq!(tracker.observe(&result(true, 0)), None);
+        assert_eq!(
+            tracker.observe(&result(false, 30)),
+            Some(AlertEvent::Firing)
+        );
+        assert_eq!(tracker.observe(&result(false, 60)), None);
+        assert_eq!(
+            tracker.observe(&result(true, 90)),
+            Some(AlertEvent::Resolved)
+        );
+        assert_eq!(tracker.observe(&result(true, 120)), None);
+    }
+
+    #[test]
+    fn test_tracker_fires_when_first_seen_unhealthy() {
+        let mut tracker = AlertTracker::new(0);
+        assert_eq!(tracker.observe(&result(false, 0)), Some(AlertEvent::Firing));
+    }
+
+    #[test]
+    fn test_tracker_renotifies_after_interval() {
+        let mut tracker = AlertTracker::new(300);
+        assert_eq!(tracker.observe(&result(false, 0)), Some(AlertEvent::Firing));
+        assert_eq!(tracker.observe(&result(false, 299)), None);
+        assert_eq!(
+            tracker.observe(&result(false, 300)),
+            Some(AlertEvent::Firing)
+        );
+        assert_eq!(tracker.observe(&result(false, 400)), None);
+    }
+
+    fn notifier(config: Value) -> Notifier {
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_payload_formats() {
+        let r = result(false, 1);
+        let slack = payload(
+            &notifier(json!({"url": "http://x", "format": "slack"})),
+            AlertEvent::Firing,
+            &r,
+        );
+        assert_eq!(slack["text"], "[FIRING] l1/sync: not_synced");
+        let discord = payload(
+            &notifier(json!({"url": "http://x", "format": "discord"})),
+            AlertEvent::Resolved,
+            &r,
+        );
+        assert_eq!(discord["content"], "[RESOLVED] l1/sync: not_synced");
+        let generic = payload(
+            &notifier(json!({"url": "http://x"})),
+            AlertEvent::Firing,
+            &r,
+        );
+        assert_eq!(generic["event"], "firing");
+        assert_eq!(generic["check"], "sync");
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_transitions() {
+        let mut server = Server::new_async().await;
+        let firing = server
+            .mock("POST", "/hook")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"event":"firing","target":"l1"}"#.to_string(),
+            ))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+        let resolved = server
+            .mock("POST", "/hook")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"event":"resolved"}"#.to_string(),
+            ))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut webhook = WebhookNotifier::new(notifier(json!({
+            "url": format!("{}/hook", server.url()),
+            "renotify_minutes": 0,
+        })));
+        webhook.process(&result(false, 0)).await;
+        webhook.process(&result(false, 30)).await;
+        webhook.process(&result(true, 60)).await;
+
+        firing.assert_async().await;
+        resolved.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_pagerduty_trigger_and_resolve_share_dedup_key() {
+        let mut server = Server::new_async().await;
+        let trigger = server
+            .mock("POST", "/v2/enqueue")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"routing_key":"rk","event_action":"trigger","dedup_key":"monitor/l1/sync",
+                    "payload":{"source":"l1","severity":"critical","component":"sync"}}"#
+                    .to_string(),
+            ))
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+        let resolve = server
+            .mock("POST", "/v2/enqueue")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"event_action":"resolve","dedup_key":"monitor/l1/sync"}"#.to_string(),
+            ))
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut pagerduty = WebhookNotifier::new(notifier(json!({
+            "url": format!("{}/v2/enqueue", server.url()),
+            "format": "pagerduty",
+            "routing_key": "rk",
+        })));
+        pagerduty.process(&result(false, 0)).await;
+        pagerduty.process(&result(true, 30)).await;
+
+        trigger.assert_async().await;
+        resolve.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_resolve_is_sent_again_next_round() {
+        let mut server = Server::new_async().await;
+        let trigger = server
+            .mock("POST", "/v2/enqueue")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"event_action":"trigger"}"#.to_string(),
+            ))
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+        let failed_resolve = server
+            .mock("POST", "/v2/enqueue")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"event_action":"resolve"}"#.to_string(),
+            ))
+            .with_status(500)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut pagerduty = WebhookNotifier::new(notifier(json!({
+            "url": format!("{}/v2/enqueue", server.url()),
+            "format": "pagerduty",
+            "routing_key": "rk",
+        })));
+        pagerduty.process(&result(false, 0)).await;
+        pagerduty.process(&result(true, 30)).await;
+        trigger.assert_async().await;
+        failed_resolve.assert_async().await;
+        failed_resolve.remove_async().await;
+
+        let resolve = server
+            .mock("POST", "/v2/enqueue")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"event_action":"resolve"}"#.to_string(),
+            ))
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+        pagerduty.process(&result(true, 60)).await;
+        pagerduty.process(&result(true, 90)).await;
+        resolve.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_trigger_is_sent_again_next_round() {
+        let mut server = Server::new_async().await;
+        let failed = server
+            .mock("POST", "/hook")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        let mut webhook = WebhookNotifier::new(notifier(json!({
+            "url": format!("{}/hook", server.url()),
+            "renotify_minutes": 60,
+        })));
+        webhook.process(&result(false, 0)).await;
+        failed.assert_async().await;
+        failed.remove_async().await;
+
+        let firing = server
+            .mock("POST", "/hook")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"event":"firing"}"#.to_string(),
+            ))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+        webhook.process(&result(false, 30)).await;
+        webhook.process(&result(false, 60)).await;
+        firing.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_opsgenie_creates_and_closes_by_alias() {
+        let mut server = Server::new_async().await;
+        let create = server
+            .mock("POST", "/v2/alerts")
+            .match_header("authorization", "GenieKey key")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"alias":"monitor/l1/sync","entity":"l1"}"#.to_string(),
+            ))
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+        let close = server
+            .mock("POST", "/v2/alerts/monitor%2Fl1%2Fsync/close")
+            .match_query(Matcher::UrlEncoded(
+                "identifierType".to_string(),
+                "alias".to_string(),
+            ))
+            .match_header("authorization", "GenieKey key")
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut opsgenie = WebhookNotifier::new(notifier(json!({
+            "url": format!("{}/v2/alerts", server.url()),
+            "format": "opsgenie",
+            "api_key": "key",
+        })));
+        opsgenie.process(&result(false, 0)).await;
+        opsgenie.process(&result(true, 30)).await;
+
+        create.assert_async().await;
+        close.assert_async().await;
+    }
+
+    #[test]
+    fn test_opsgenie_close_endpoint_encodes_the_alias() {
+        let opsgenie = notifier(json!({
+            "url": "https://api.opsgenie.com/v2/alerts/",
+            "format": "opsgenie",
+            "api_key": "key",
+        }));
+        let mut r = result(true, 0);
+        r.target = "l1 #2?x=%".to_string();
+        r.check = "balance:0xab".to_string();
+        assert_eq!(
+            endpoint(&opsgenie, AlertEvent::Resolved, &r),
+            "https://api.opsgenie.com/v2/alerts/monitor%2Fl1%20%232%3Fx=%25%2Fbalance:0xab/close?identifierType=alias"
+        );
+        assert_eq!(
+            endpoint(&opsgenie, AlertEvent::Firing, &r),
+            "https://api.opsgenie.com/v2/alerts/"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_filter_skips_other_checks() {
+        let mut server = Server::new_async().await;
+        let hook = server.mock("POST", "/hook").expect(0).create_async().await;
+
+        let mut webhook = WebhookNotifier::new(notifier(json!({
+            "url": format!("{}/hook", server.url()),
+            "checks": ["balance"],
+        })));
+        webhook.process(&result(false, 0)).await;
+
+        hook.assert_async().await;
+    }
+}
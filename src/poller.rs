@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::{interval, Duration};
+use tracing::Instrument;
+
+use crate::config::{Check, CheckConfig, Config, Target};
+use crate::heads::{self, HeadTracker};
+use crate::history::History;
+use crate::hysteresis::HysteresisStore;
+use crate::kuma;
+use crate::logs::{self, WatcherState};
+use crate::notify::WebhookNotifier;
+use crate::state::{CheckResult, MonitorState};
+use crate::telemetry;
+use crate::{assertions, check_balance, finalized_latest_diff, get_block_number, BatchRejections};
+
+/// State the poller carries between rounds.
+#[derive(Default)]
+struct PollerState {
+    watchers: HashMap<(String, String), WatcherState>,
+    /// Head seen by the previous round, per target, for the `sync` check.
+    heads: HashMap<String, i64>,
+    /// Smoothing history for thresholded checks, separate from HTTP probes.
+    hysteresis: Arc<HysteresisStore>,
+    notifiers: Vec<WebhookNotifier>,
+    client: reqwest::Client,
+    history: Option<Arc<History>>,
+    /// Subscribed heads, for the `sync` check of targets with a `ws` endpoint.
+    subscriptions: Option<Arc<HeadTracker>>,
+    batch_rejections: Arc<BatchRejections>,
+}
+
+/// Background poller: runs the configured checks every `poll_interval_secs`,
+/// records their results in the shared `MonitorState` (and `history`, when enabled)
+/// and notifies on state changes.
+pub async fn run(
+    config: Arc<Config>,
+    state: Arc<MonitorState>,
+    history: Option<Arc<History>>,
+    subscriptions: Arc<HeadTracker>,
+    batch_rejections: Arc<BatchRejections>,
+) {
+    let mut poller = PollerState {
+        history,
+        subscriptions: Some(subscriptions),
+        batch_rejections,
+        notifiers: config
+            .notifiers
+            .iter()
+            .cloned()
+            .map(WebhookNotifier::new)
+            .collect(),
+        ..Default::default()
+    };
+    let mut ticker = interval(Duration::from_secs(config.poll_interval_secs));
+
+    loop {
+        ticker.tick().await;
+        poll_once(&config, &state, &mut poller).await;
+    }
+}
+
+fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Turn an HTTP handler's reply into a check result: 2xx is healthy, and the JSON
+/// body becomes the detail. Bodies without a `status` field fall back to the code.
+async fn reply_to_result(target: &str, check: String, reply: impl warp::Reply) -> CheckResult {
+    let response = reply.into_response();
+    let code = response.status();
+    let detail: serde_json::Value = match warp::hyper::body::to_bytes(response.into_body()).await {
+        Ok(body) => serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+        Err(_) => serde_json::Value::Null,
+    };
+    let status = match detail.get("status").and_then(|s| s.as_str()) {
+        Some(s) => s.to_string(),
+        None if code.is_success() => "ok".to_string(),
+        None => code.as_u16().to_string(),
+    };
+
+    CheckResult {
+        target: target.to_string(),
+        check,
+        healthy: code.is_success(),
+        status,
+        detail,
+        checked_at: unix_now(),
+        latency_ms: 0,
+    }
+}
+
+/// Compare the head with the previous round's. The first round only records a baseline.
+async fn run_sync(target: &Target, heads: &mut HashMap<String, i64>) -> Option<CheckResult> {
+    let now = unix_now();
+    let head = match get_block_number(Some(target.rpc.clone())).await {
+        Ok(n) => n,
+        Err(e) => {
+            return Some(CheckResult {
+                target: target.name.clone(),
+                check: CheckConfig::Sync.name(),
+                healthy: false,
+                status: format!("error: {}", e),
+                detail: serde_json::Value::Null,
+                checked_at: now,
+                latency_ms: 0,
+            })
+        }
+    };
+    let previous = heads.insert(target.name.clone(), head)?;
+    let synced = head != previous;
+
+    Some(CheckResult {
+        target: target.name.clone(),
+        check: CheckConfig::Sync.name(),
+        healthy: synced,
+        status: if synced { "synced" } else { "not_synced" }.to_string(),
+        detail: serde_json::json!({
+            "block_number_hex": format!("0x{:x}", head),
+            "block_number_decimal": head,
+            "previous_block_number": previous,
+        }),
+        checked_at: now,
+        latency_ms: 0,
+    })
+}
+
+async fn run_check(
+    target: &Target,
+    check: &Check,
+    poller: &mut PollerState,
+) -> Option<CheckResult> {
+    let rpc = Some(target.rpc.clone());
+    let store = poller.hysteresis.clone();
+    match &check.kind {
+        CheckConfig::Sync => match (&target.ws, &poller.subscriptions) {
+            (Some(_), Some(tracker)) => Some(heads::sync_result(target, tracker, unix_now_ms())),
+            _ => run_sync(target, &mut poller.heads).await,
+        },
+        CheckConfig::FinalizedLatestDiff {
+            diff,
+            warn,
+            damping,
+        } => {
+            let batch_rejections = poller.batch_rejections.clone();
+            let reply =
+                finalized_latest_diff(rpc, Some(*diff), *warn, *damping, store, batch_rejections)
+                    .await
+                    .ok()?;
+            Some(reply_to_result(&target.name, check.name(), reply).await)
+        }
+        CheckConfig::Balance {
+            address,
+            alert,
+            warn,
+            damping,
+        } => {
+            let reply = check_balance(
+                rpc,
+                address.clone(),
+                alert.clone(),
+                warn.clone(),
+                *damping,
+                store,
+            )
+            .await
+            .ok()?;
+            Some(reply_to_result(&target.name, check.name(), reply).await)
+        }
+    }
+}
+
+/// Write a round's results and apply the retention window.
+async fn persist(history: Arc<History>, results: &[CheckResult], config: &Config, now: u64) {
+    let results = results.to_vec();
+    let retention_days = config
+        .history
+        .as_ref()
+        .map(|h| h.retention_days)
+        .unwrap_or(0);
+    // SQLite calls block; keep them off the async worker threads.
+    let outcome = tokio::task::spawn_blocking(move || {
+        for result in &results {
+            history.insert(result)?;
+        }
+        if retention_days > 0 {
+            history.prune(now.saturating_sub(retention_days * 86_400))?;
+        }
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await;
+    match outcome {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => tracing::error!(error = %e, "failed to write check history"),
+        Err(e) => tracing::error!(error = %e, "failed to write check history"),
+    }
+}
+
+/// Run one check under its own correlation ID, so its RPC calls can be traced
+/// like those of an HTTP request.
+async fn in_check_span<F: Future>(target: &str, check: &str, fut: F) -> F::Output {
+    let id = telemetry::new_request_id();
+    let span = tracing::info_span!("check", target, check, request_id = %id);
+    telemetry::with_request_id(id, fut).instrument(span).await
+}
+
+fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
+
+/// One polling round over every target.
+async fn poll_once(config: &Config, state: &MonitorState, poller: &mut PollerState) {
+    let mut results = Vec::new();
+
+    for target in &config.targets {
+        for check in &target.checks {
+            let started = Instant::now();
+            let run = run_check(target, check, poller);
+            if let Some(mut result) = in_check_span(&target.name, &check.name(), run).await {
+                result.latency_ms = elapsed_ms(started);
+                results.push(result);
+            }
+        }
+
+        for assertion in &target.assertions {
+            let started = Instant::now();
+            let name = format!("assertion:{}", assertion.name);
+            let run = assertions::run_assertion(&target.name, &target.rpc, assertion);
+            let (code, response) = in_check_span(&target.name, &name, run).await;
+            let reply = warp::reply::with_status(warp::reply::json(&response), code);
+            let mut result = reply_to_result(&target.name, name, reply).await;
+            result.latency_ms = elapsed_ms(started);
+            results.push(result);
+        }
+
+        for watcher in &target.log_watchers {
+            let started = Instant::now();
+            let now = unix_now();
+            let watcher_state = poller
+                .watchers
+                .entry((target.name.clone(), watcher.name.clone()))
+                .or_insert_with(|| WatcherState::new(now));
+
+            let scan = logs::scan(&target.rpc, watcher, watcher_state, now);
+            let scanned = in_check_span(&target.name, &logs::check_name(watcher), scan).await;
+            let mut result = match scanned {
+                Ok(()) => logs::evaluate(&target.name, watcher, watcher_state, now),
+                Err(e) => CheckResult {
+                    target: target.name.clone(),
+                    check: logs::check_name(watcher),
+                    healthy: false,
+                    status: format!("error: {}", e),
+                    detail: serde_json::Value::Null,
+                    checked_at: now,
+                    latency_ms: 0,
+                },
+            };
+            result.latency_ms = elapsed_ms(started);
+            results.push(result);
+        }
+    }
+
+    if let Some(history) = poller.history.clone() {
+        persist(history, &results, config, unix_now()).await;
+    }
+
+    for result in results {
+        if result.healthy {
+            tracing::info!(target = %result.target, check = %result.check, status = %result.status, latency_ms = result.latency_ms, "check passed");
+        } else {
+            tracing::warn!(target = %result.target, check = %result.check, status = %result.status, latency_ms = result.latency_ms, "check failed");
+        }
+        for notifier in &mut poller.notifiers {
+            notifier.process(&result).await;
+        }
+        if let Some(push) = config.target(&result.target).and_then(|t| t.kuma.as_ref()) {
+            kuma::push(&poller.client, push, &result).await;
+        }
+        state.record(result);
+    }
+    state.round_completed();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+    use crate::tests::echo_rpc_id;
+    use mockito::{Matcher, Server};
+
+    #[tokio::test]
+    async fn test_poll_once_records_watcher_results() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"method":"eth_blockNumber"}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#))
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"method":"eth_getLogs"}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(
+                r#"{"jsonrpc":"2.0","id":1,"result":[{"blockNumber":"0x10"}]}"#,
+            ))
+            .create_async()
+            .await;
+
+        let config = parse_config(&format!(
+            r#"{{"targets":[{{"name":"l1","rpc":"{}","log_watchers":[{{
+                "name":"owner",
+                "contract":"0x0000000000000000000000000000000000000001",
+                "event":"OwnershipTransferred(address,address)",
+                "alert":{{"kind":"any_occurrence"}}}}]}}]}}"#,
+            server.url()
+        ))
+        .unwrap();
+        let state = MonitorState::default();
+
+        poll_once(&config, &state, &mut PollerState::default()).await;
+
+        let result = state.get("l1", "log_watcher:owner").unwrap();
+        assert!(!result.healthy);
+        assert_eq!(result.status, "event_seen");
+        assert_eq!(state.rounds(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_records_rpc_failure() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(500)
+            .create_async()
+            .await;
+
+        let config = parse_config(&format!(
+            r#"{{"targets":[{{"name":"l1","rpc":"{}","log_watchers":[{{
+                "name":"deposits",
+                "contract":"0x0000000000000000000000000000000000000001",
+                "event":"DepositFinalized(address)",
+                "alert":{{"kind":"absence"}}}}]}}]}}"#,
+            server.url()
+        ))
+        .unwrap();
+        let state = MonitorState::default();
+
+        poll_once(&config, &state, &mut PollerState::default()).await;
+
+        let result = state.get("l1", "log_watcher:deposits").unwrap();
+        assert!(!result.healthy);
+        assert!(result.status.starts_with("error"));
+    }
+
+    async fn mock_head(server: &mut mockito::ServerGuard, block: u64) -> mockito::Mock {
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"method":"eth_blockNumber"}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":"0x{:x}"}}"#,
+                block
+            )))
+            .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_sync_check_compares_rounds_and_notifies() {
+        let mut server = Server::new_async().await;
+        let hook = server
+            .mock("POST", "/hook")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"event":"firing","check":"sync","status":"not_synced"}"#.to_string(),
+            ))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = parse_config(&format!(
+            r#"{{"targets":[{{"name":"l1","rpc":"{url}","checks":[{{"kind":"sync"}}]}}],
+                "notifiers":[{{"url":"{url}/hook"}}]}}"#,
+            url = server.url()
+        ))
+        .unwrap();
+        let state = MonitorState::default();
+        let mut poller = PollerState {
+            notifiers: config
+                .notifiers
+                .iter()
+                .cloned()
+                .map(WebhookNotifier::new)
+                .collect(),
+            ..Default::default()
+        };
+
+        let head = mock_head(&mut server, 100).await;
+        poll_once(&config, &state, &mut poller).await;
+        // The first round only records a baseline.
+        assert!(state.get("l1", "sync").is_none());
+
+        head.remove_async().await;
+        let head = mock_head(&mut server, 101).await;
+        poll_once(&config, &state, &mut poller).await;
+        assert_eq!(state.get("l1", "sync").unwrap().status, "synced");
+
+        head.remove_async().await;
+        mock_head(&mut server, 101).await;
+        poll_once(&config, &state, &mut poller).await;
+        assert!(!state.get("l1", "sync").unwrap().healthy);
+        hook.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_sync_check_uses_subscribed_head_age() {
+        let config = parse_config(
+            r#"{"targets":[{"name":"l1","rpc":"http://127.0.0.1:1","ws":"ws://127.0.0.1:1",
+                "head_stall_secs":30,"checks":[{"kind":"sync"}]}]}"#,
+        )
+        .unwrap();
+        let tracker = Arc::new(HeadTracker::default());
+        tracker.observe(
+            "l1",
+            heads::Head {
+                number: 100,
+                hash: None,
+                arrived_at_ms: unix_now_ms() - 45_000,
+                source: heads::HeadSource::WebSocket,
+            },
+        );
+        let state = MonitorState::default();
+        let mut poller = PollerState {
+            subscriptions: Some(tracker),
+            ..Default::default()
+        };
+
+        // No RPC call: the first round already has a result.
+        poll_once(&config, &state, &mut poller).await;
+        let result = state.get("l1", "sync").unwrap();
+        assert_eq!(result.status, "stalled");
+        assert_eq!(result.detail["block_number_decimal"], 100);
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_records_balance_check() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"method":"eth_getBalance"}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#))
+            .create_async()
+            .await;
+
+        let config = parse_config(&format!(
+            r#"{{"targets":[{{"name":"l1","rpc":"{}","checks":[{{
+                "kind":"balance",
+                "address":"0x0000000000000000000000000000000000000001",
+                "alert":"100"}}]}}]}}"#,
+            server.url()
+        ))
+        .unwrap();
+        let state = MonitorState::default();
+
+        poll_once(&config, &state, &mut PollerState::default()).await;
+
+        let result = state
+            .get("l1", "balance:0x0000000000000000000000000000000000000001")
+            .unwrap();
+        assert!(!result.healthy);
+        assert_eq!(result.status, "balance_low");
+        assert_eq!(result.detail["balance_decimal"], "16");
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_writes_history() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#))
+            .create_async()
+            .await;
+
+        let config = parse_config(&format!(
+            r#"{{"history":{{"path":":memory:"}},
+                "targets":[{{"name":"l1","rpc":"{}","checks":[{{
+                "kind":"balance",
+                "address":"0x0000000000000000000000000000000000000001",
+                "alert":"1"}}]}}]}}"#,
+            server.url()
+        ))
+        .unwrap();
+        let history = Arc::new(History::open(":memory:").unwrap());
+        let mut poller = PollerState {
+            history: Some(history.clone()),
+            ..Default::default()
+        };
+
+        poll_once(&config, &MonitorState::default(), &mut poller).await;
+
+        let rows = history.query("l1", "", 0, u64::MAX, 10).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].status, "balance_sufficient");
+        assert_eq!(rows[0].detail["balance_decimal"], "16");
+    }
+}
@@ -0,0 +1,200 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration};
+
+use crate::config::Config;
+use crate::state::MonitorState;
+
+const HEARTBEAT_INTERVAL_MS: u64 = 1_000;
+/// The runtime counts as unresponsive when the heartbeat task has not run for this long.
+const HEARTBEAT_STALE_MS: u64 = 10_000;
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Health of the monitor process itself, independent of any monitored node.
+#[derive(Default)]
+pub struct Liveness {
+    heartbeat_ms: AtomicU64,
+    tasks: Mutex<Vec<(&'static str, JoinHandle<()>)>>,
+}
+
+impl Liveness {
+    /// Start the heartbeat task that proves the runtime still schedules work.
+    pub fn spawn_heartbeat(self: &Arc<Self>) {
+        self.heartbeat_ms.store(unix_millis(), Ordering::Relaxed);
+        let liveness = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(HEARTBEAT_INTERVAL_MS));
+            loop {
+                ticker.tick().await;
+                liveness
+                    .heartbeat_ms
+                    .store(unix_millis(), Ordering::Relaxed);
+            }
+        });
+    }
+
+    /// Track a long-running background task; liveness fails once it exits or panics.
+    pub fn watch(&self, name: &'static str, handle: JoinHandle<()>) {
+        self.tasks.lock().unwrap().push((name, handle));
+    }
+}
+
+#[derive(Serialize)]
+struct LivezResponse {
+    status: String,
+    heartbeat_age_ms: u64,
+    tasks: BTreeMap<&'static str, &'static str>,
+}
+
+/// HTTP handler for `/livez`: 503 when the heartbeat is stale or a watched background
+/// task has stopped. Makes no RPC calls, so a sick node never restarts the monitor.
+pub async fn livez(liveness: Arc<Liveness>) -> Result<impl warp::Reply, warp::Rejection> {
+    let heartbeat_age_ms =
+        unix_millis().saturating_sub(liveness.heartbeat_ms.load(Ordering::Relaxed));
+    let tasks: BTreeMap<&'static str, &'static str> = liveness
+        .tasks
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, handle)| {
+            let state = if handle.is_finished() {
+                "stopped"
+            } else {
+                "running"
+            };
+            (*name, state)
+        })
+        .collect();
+
+    let alive = heartbeat_age_ms < HEARTBEAT_STALE_MS && tasks.values().all(|s| *s == "running");
+    let response = LivezResponse {
+        status: if alive { "alive" } else { "dead" }.to_string(),
+        heartbeat_age_ms,
+        tasks,
+    };
+    let code = if alive {
+        warp::http::StatusCode::OK
+    } else {
+        warp::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&response), code))
+}
+
+#[derive(Serialize)]
+struct ReadyzResponse {
+    status: String,
+    config_loaded: bool,
+    targets: usize,
+    poll_rounds: u64,
+}
+
+/// HTTP handler for `/readyz`: ready once the config is loaded (always true by the time
+/// the server listens) and, when targets are configured, the first poller round has
+/// completed. Makes no RPC calls.
+pub async fn readyz(
+    config: Arc<Config>,
+    state: Arc<MonitorState>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let poll_rounds = state.rounds();
+    let ready = config.targets.is_empty() || poll_rounds > 0;
+    let response = ReadyzResponse {
+        status: if ready { "ready" } else { "not_ready" }.to_string(),
+        config_loaded: true,
+        targets: config.targets.len(),
+        poll_rounds,
+    };
+    let code = if ready {
+        warp::http::StatusCode::OK
+    } else {
+        warp::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&response), code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    async fn json(reply: impl warp::Reply) -> (warp::http::StatusCode, serde_json::Value) {
+        let response = reply.into_response();
+        let status = response.status();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_livez_alive_with_running_tasks() {
+        let liveness = Arc::new(Liveness::default());
+        liveness.spawn_heartbeat();
+        liveness.watch("poller", tokio::spawn(std::future::pending()));
+
+        let (status, body) = json(livez(liveness).await.unwrap()).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(body["tasks"]["poller"], "running");
+    }
+
+    #[tokio::test]
+    async fn test_livez_dead_when_task_stops() {
+        let liveness = Arc::new(Liveness::default());
+        liveness.spawn_heartbeat();
+        let handle = tokio::spawn(async {});
+        while !handle.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        liveness.watch("poller", handle);
+
+        let (status, body) = json(livez(liveness).await.unwrap()).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["tasks"]["poller"], "stopped");
+    }
+
+    #[tokio::test]
+    async fn test_livez_dead_when_heartbeat_stale() {
+        // Never started, so the heartbeat is the epoch.
+        let liveness = Arc::new(Liveness::default());
+        let (status, _) = json(livez(liveness).await.unwrap()).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_waits_for_first_round() {
+        let config =
+            Arc::new(parse_config(r#"{"targets":[{"name":"l1","rpc":"http://x"}]}"#).unwrap());
+        let state = Arc::new(MonitorState::default());
+
+        let (status, body) = json(readyz(config.clone(), state.clone()).await.unwrap()).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "not_ready");
+
+        state.round_completed();
+        let (status, _) = json(readyz(config, state).await.unwrap()).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_without_targets_is_ready() {
+        let (status, _) = json(
+            readyz(
+                Arc::new(Config::default()),
+                Arc::new(MonitorState::default()),
+            )
+            .await
+            .unwrap(),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+    }
+}
@@ -0,0 +1,464 @@
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{eth_call, get_block_number, is_valid_eth_address};
+
+/// How many of the newest dispute games to search for one of the requested type.
+const MAX_GAMES_SCANNED: u64 = 50;
+
+/// Where the proposer posts L2 state commitments on L1.
+pub enum ProposalSource {
+    /// Legacy `L2OutputOracle`.
+    OutputOracle(String),
+    /// Fault-proof `DisputeGameFactory`, optionally restricted to one game type.
+    DisputeGameFactory(String, Option<u32>),
+}
+
+/// The newest proposal found on L1.
+struct Proposal {
+    l2_block: u64,
+    /// L1 timestamp of the proposal.
+    timestamp: u64,
+}
+
+#[derive(Serialize)]
+struct ProposalLagResponse {
+    source: String,
+    contract: String,
+    proposed_l2_block: u64,
+    proposal_timestamp: u64,
+    l2_head: i64,
+    lag_blocks: i64,
+    lag_seconds: u64,
+    max_blocks: Option<i64>,
+    max_seconds: Option<u64>,
+    status: String,
+}
+
+/// Read 32-byte return word `index` as a `u64`, rejecting values that do not fit.
+fn word_u64(data: &[u8], index: usize) -> Result<u64, Box<dyn std::error::Error>> {
+    let word = data
+        .get(index * 32..(index + 1) * 32)
+        .ok_or_else(|| format!("return data too short for word {}", index))?;
+    if word[..24].iter().any(|b| *b != 0) {
+        return Err(format!("return word {} does not fit in 64 bits", index).into());
+    }
+    Ok(u64::from_be_bytes(word[24..].try_into().unwrap()))
+}
+
+/// Read 32-byte return word `index` as an address.
+fn word_address(data: &[u8], index: usize) -> Result<String, Box<dyn std::error::Error>> {
+    let word = data
+        .get(index * 32..(index + 1) * 32)
+        .ok_or_else(|| format!("return data too short for word {}", index))?;
+    Ok(format!("0x{}", crate::encode_hex(&word[12..])))
+}
+
+fn uint_arg(value: u64) -> String {
+    format!("0x{:064x}", value)
+}
+
+async fn latest_oracle_proposal(
+    l1_rpc: Option<String>,
+    oracle: &str,
+) -> Result<Proposal, Box<dyn std::error::Error>> {
+    let index_data = eth_call(l1_rpc.clone(), oracle, "latestOutputIndex()", "").await?;
+    let index = word_u64(&index_data, 0)?;
+
+    // getL2Output returns (bytes32 outputRoot, uint128 timestamp, uint128 l2BlockNumber).
+    let output = eth_call(l1_rpc, oracle, "getL2Output(uint256)", &uint_arg(index)).await?;
+    Ok(Proposal {
+        timestamp: word_u64(&output, 1)?,
+        l2_block: word_u64(&output, 2)?,
+    })
+}
+
+async fn latest_game_proposal(
+    l1_rpc: Option<String>,
+    factory: &str,
+    game_type: Option<u32>,
+) -> Result<Proposal, Box<dyn std::error::Error>> {
+    let count_data = eth_call(l1_rpc.clone(), factory, "gameCount()", "").await?;
+    let count = word_u64(&count_data, 0)?;
+
+    for index in (count.saturating_sub(MAX_GAMES_SCANNED)..count).rev() {
+        // gameAtIndex returns (uint32 gameType, uint64 timestamp, address proxy).
+        let game = eth_call(
+            l1_rpc.clone(),
+            factory,
+            "gameAtIndex(uint256)",
+            &uint_arg(index),
+        )
+        .await?;
+        if let Some(wanted) = game_type {
+            if word_u64(&game, 0)? != wanted as u64 {
+                continue;
+            }
+        }
+        let timestamp = word_u64(&game, 1)?;
+        let proxy = word_address(&game, 2)?;
+
+        let block_data = eth_call(l1_rpc, &proxy, "l2BlockNumber()", "").await?;
+        return Ok(Proposal {
+            l2_block: word_u64(&block_data, 0)?,
+            timestamp,
+        });
+    }
+
+    Err(format!(
+        "no dispute game found in the newest {} games",
+        MAX_GAMES_SCANNED
+    )
+    .into())
+}
+
+/// HTTP handler for `/proposal_lag`. Compares the newest L2 block proposed on L1 with
+/// the L2 head and returns 503 when the gap exceeds `max_blocks` or the newest proposal
+/// is older than `max_seconds`; 500 when either chain cannot be queried.
+pub async fn check_proposal_lag(
+    l1_rpc: Option<String>,
+    l2_rpc: Option<String>,
+    source: Option<ProposalSource>,
+    max_blocks: Option<i64>,
+    max_seconds: Option<u64>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut response = ProposalLagResponse {
+        source: "".to_string(),
+        contract: "".to_string(),
+        proposed_l2_block: 0,
+        proposal_timestamp: 0,
+        l2_head: 0,
+        lag_blocks: 0,
+        lag_seconds: 0,
+        max_blocks,
+        max_seconds,
+        status: "".to_string(),
+    };
+
+    let source = match source {
+        Some(ProposalSource::OutputOracle(a)) | Some(ProposalSource::DisputeGameFactory(a, _))
+            if !is_valid_eth_address(&a) =>
+        {
+            None
+        }
+        s => s,
+    };
+
+    // Errors are stringified straight away: `Box<dyn Error>` is not `Send` and must
+    // not be held across the L2 head query below.
+    let proposal = match &source {
+        Some(ProposalSource::OutputOracle(oracle)) => {
+            response.source = "l2_output_oracle".to_string();
+            response.contract = oracle.clone();
+            latest_oracle_proposal(l1_rpc, oracle)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        Some(ProposalSource::DisputeGameFactory(factory, game_type)) => {
+            response.source = "dispute_game_factory".to_string();
+            response.contract = factory.clone();
+            latest_game_proposal(l1_rpc, factory, *game_type)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        None => {
+            response.status =
+                "error: exactly one valid oracle or factory address is required".to_string();
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&response),
+                warp::http::StatusCode::BAD_REQUEST,
+            ));
+        }
+    };
+
+    let proposal = match proposal {
+        Ok(p) => p,
+        Err(e) => {
+            response.status = format!("error: {}", e);
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&response),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+    response.proposed_l2_block = proposal.l2_block;
+    response.proposal_timestamp = proposal.timestamp;
+
+    let l2_head = match get_block_number(l2_rpc).await {
+        Ok(n) => n,
+        Err(e) => {
+            response.status = format!("error: {}", e);
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&response),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    response.l2_head = l2_head;
+    response.lag_blocks = l2_head - proposal.l2_block as i64;
+    response.lag_seconds = now.saturating_sub(proposal.timestamp);
+
+    let lagging = max_blocks.map(|m| response.lag_blocks > m).unwrap_or(false)
+        || max_seconds
+            .map(|m| response.lag_seconds > m)
+            .unwrap_or(false);
+
+    if lagging {
+        response.status = "proposal_lagging".to_string();
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    } else {
+        response.status = "proposals_current".to_string();
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::echo_rpc_id;
+    use crate::{encode_hex, function_selector};
+    use mockito::{Matcher, Server};
+    use warp::Reply;
+
+    const ORACLE: &str = "0x0000000000000000000000000000000000000001";
+    const FACTORY: &str = "0x0000000000000000000000000000000000000002";
+    const GAME_PROXY: &str = "0x00000000000000000000000000000000000000aa";
+
+    fn words(values: &[u64]) -> String {
+        let body: String = values.iter().map(|v| format!("{:064x}", v)).collect();
+        format!("0x{}", body)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    async fn mock_call(server: &mut mockito::ServerGuard, to: &str, calldata: &str, result: &str) {
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(format!(
+                r#"{{"method":"eth_call","params":[{{"to":"{}","data":"{}"}}]}}"#,
+                to, calldata
+            )))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":"{}"}}"#,
+                result
+            )))
+            .create_async()
+            .await;
+    }
+
+    fn calldata(signature: &str, args: &[u64]) -> String {
+        let args: String = args.iter().map(|v| format!("{:064x}", v)).collect();
+        format!("0x{}{}", encode_hex(&function_selector(signature)), args)
+    }
+
+    async fn mock_l2_head(server: &mut mockito::ServerGuard, head: u64) {
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":"0x{:x}"}}"#,
+                head
+            )))
+            .create_async()
+            .await;
+    }
+
+    async fn mock_oracle(server: &mut mockito::ServerGuard, l2_block: u64, timestamp: u64) {
+        mock_call(
+            server,
+            ORACLE,
+            &calldata("latestOutputIndex()", &[]),
+            &words(&[7]),
+        )
+        .await;
+        mock_call(
+            server,
+            ORACLE,
+            &calldata("getL2Output(uint256)", &[7]),
+            &words(&[0xabcd, timestamp, l2_block]),
+        )
+        .await;
+    }
+
+    async fn lag_json(
+        l1: &mockito::ServerGuard,
+        l2: &mockito::ServerGuard,
+        source: ProposalSource,
+        max_blocks: Option<i64>,
+        max_seconds: Option<u64>,
+    ) -> (warp::http::StatusCode, serde_json::Value) {
+        let response = check_proposal_lag(
+            Some(l1.url()),
+            Some(l2.url()),
+            Some(source),
+            max_blocks,
+            max_seconds,
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let status = response.status();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[test]
+    fn test_word_u64_rejects_overflow() {
+        let mut data = vec![0u8; 32];
+        data[0] = 1;
+        assert!(word_u64(&data, 0).is_err());
+        assert!(word_u64(&data, 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_oracle_proposal_within_limits_returns_ok() {
+        let mut l1 = Server::new_async().await;
+        let mut l2 = Server::new_async().await;
+        mock_oracle(&mut l1, 1_000, now() - 60).await;
+        mock_l2_head(&mut l2, 1_500).await;
+
+        let (status, json) = lag_json(
+            &l1,
+            &l2,
+            ProposalSource::OutputOracle(ORACLE.to_string()),
+            Some(1_800),
+            Some(3_600),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(json["proposed_l2_block"], 1_000);
+        assert_eq!(json["lag_blocks"], 500);
+    }
+
+    #[tokio::test]
+    async fn test_oracle_proposal_block_lag_returns_unavailable() {
+        let mut l1 = Server::new_async().await;
+        let mut l2 = Server::new_async().await;
+        mock_oracle(&mut l1, 1_000, now()).await;
+        mock_l2_head(&mut l2, 5_000).await;
+
+        let (status, json) = lag_json(
+            &l1,
+            &l2,
+            ProposalSource::OutputOracle(ORACLE.to_string()),
+            Some(1_800),
+            None,
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["status"], "proposal_lagging");
+    }
+
+    #[tokio::test]
+    async fn test_oracle_proposal_time_lag_returns_unavailable() {
+        let mut l1 = Server::new_async().await;
+        let mut l2 = Server::new_async().await;
+        mock_oracle(&mut l1, 1_000, now() - 7_200).await;
+        mock_l2_head(&mut l2, 1_001).await;
+
+        let (status, _) = lag_json(
+            &l1,
+            &l2,
+            ProposalSource::OutputOracle(ORACLE.to_string()),
+            None,
+            Some(3_600),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_dispute_game_factory_filters_game_type() {
+        let mut l1 = Server::new_async().await;
+        let mut l2 = Server::new_async().await;
+        let proxy_word = u64::from_str_radix(&GAME_PROXY[GAME_PROXY.len() - 2..], 16).unwrap();
+        mock_call(
+            &mut l1,
+            FACTORY,
+            &calldata("gameCount()", &[]),
+            &words(&[2]),
+        )
+        .await;
+        // Newest game has type 1, the one before has the requested type 0.
+        mock_call(
+            &mut l1,
+            FACTORY,
+            &calldata("gameAtIndex(uint256)", &[1]),
+            &words(&[1, now(), 0xbb]),
+        )
+        .await;
+        mock_call(
+            &mut l1,
+            FACTORY,
+            &calldata("gameAtIndex(uint256)", &[0]),
+            &words(&[0, now() - 30, proxy_word]),
+        )
+        .await;
+        mock_call(
+            &mut l1,
+            GAME_PROXY,
+            &calldata("l2BlockNumber()", &[]),
+            &words(&[900]),
+        )
+        .await;
+        mock_l2_head(&mut l2, 1_000).await;
+
+        let (status, json) = lag_json(
+            &l1,
+            &l2,
+            ProposalSource::DisputeGameFactory(FACTORY.to_string(), Some(0)),
+            Some(200),
+            Some(600),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(json["source"], "dispute_game_factory");
+        assert_eq!(json["proposed_l2_block"], 900);
+        assert_eq!(json["lag_blocks"], 100);
+    }
+
+    #[tokio::test]
+    async fn test_missing_source_returns_bad_request() {
+        let status = check_proposal_lag(None, None, None, None, None)
+            .await
+            .unwrap()
+            .into_response()
+            .status();
+        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+
+        let status = check_proposal_lag(
+            None,
+            None,
+            Some(ProposalSource::OutputOracle("0x01".to_string())),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .into_response()
+        .status();
+        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,138 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+use warp::http::{HeaderValue, Response, StatusCode};
+use warp::hyper::Body;
+
+/// Probe endpoints are never limited: the kubelet must always reach them.
+const EXEMPT_PATHS: [&str; 2] = ["/livez", "/readyz"];
+
+/// Clients tracked before idle (fully refilled) buckets are swept.
+const SWEEP_ABOVE: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-client-IP token bucket: up to `per_minute` requests in a burst, refilled
+/// evenly over a minute.
+pub struct RateLimiter {
+    per_minute: u32,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// A `per_minute` of 0 disables limiting.
+    pub fn new(per_minute: u32) -> Self {
+        RateLimiter {
+            per_minute,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for a request from `ip` to `path`. `Err` carries the seconds
+    /// until the client may retry.
+    pub fn check(&self, ip: IpAddr, path: &str) -> Result<(), u64> {
+        if self.per_minute == 0 || EXEMPT_PATHS.contains(&path) {
+            return Ok(());
+        }
+        self.take(ip, Instant::now())
+    }
+
+    fn take(&self, ip: IpAddr, now: Instant) -> Result<(), u64> {
+        let capacity = self.per_minute as f64;
+        let per_sec = capacity / 60.0;
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= SWEEP_ABOVE {
+            buckets.retain(|_, b| {
+                b.tokens + now.duration_since(b.updated).as_secs_f64() * per_sec < capacity
+            });
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_sec).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            // The epsilon keeps float error from rounding an exact wait up a second.
+            Err(((1.0 - bucket.tokens) / per_sec - 1e-9).ceil().max(1.0) as u64)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RateLimitedResponse {
+    retry_after_secs: u64,
+    status: String,
+}
+
+/// 429 with `Retry-After`, in the JSON shape of the other endpoints.
+pub fn too_many_requests(retry_after_secs: u64) -> Response<Body> {
+    let body = serde_json::to_string(&RateLimitedResponse {
+        retry_after_secs,
+        status: "error: rate limit exceeded".to_string(),
+    })
+    .unwrap_or_default();
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+    let headers = response.headers_mut();
+    headers.insert("content-type", HeaderValue::from_static("application/json"));
+    headers.insert("retry-after", HeaderValue::from(retry_after_secs));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const CLIENT: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1));
+    const OTHER: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 2));
+
+    #[test]
+    fn test_burst_then_retry_after() {
+        let limiter = RateLimiter::new(2);
+        let now = Instant::now();
+        assert!(limiter.take(CLIENT, now).is_ok());
+        assert!(limiter.take(CLIENT, now).is_ok());
+        // Two per minute: the next token is 30 seconds away.
+        assert_eq!(limiter.take(CLIENT, now), Err(30));
+        // Other clients have their own bucket.
+        assert!(limiter.take(OTHER, now).is_ok());
+
+        assert_eq!(limiter.take(CLIENT, now + Duration::from_secs(20)), Err(10));
+        assert!(limiter.take(CLIENT, now + Duration::from_secs(31)).is_ok());
+    }
+
+    #[test]
+    fn test_probes_and_disabled_limiter_are_not_limited() {
+        let limiter = RateLimiter::new(1);
+        assert!(limiter.check(CLIENT, "/gas").is_ok());
+        assert!(limiter.check(CLIENT, "/gas").is_err());
+        assert!(limiter.check(CLIENT, "/livez").is_ok());
+        assert!(limiter.check(CLIENT, "/readyz").is_ok());
+
+        let disabled = RateLimiter::new(0);
+        for _ in 0..100 {
+            assert!(disabled.check(CLIENT, "/gas").is_ok());
+        }
+    }
+
+    #[test]
+    fn test_too_many_requests_sets_retry_after() {
+        let response = too_many_requests(7);
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "7");
+    }
+}
@@ -0,0 +1,142 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+/// Results kept per check for sparklines.
+const RECENT_RESULTS: usize = 30;
+
+/// The latest outcome of a check run by the background poller.
+#[derive(Clone, Serialize)]
+pub struct CheckResult {
+    pub target: String,
+    pub check: String,
+    pub healthy: bool,
+    pub status: String,
+    pub detail: serde_json::Value,
+    /// Unix seconds.
+    pub checked_at: u64,
+    /// Wall-clock time the check took, including its RPC calls.
+    pub latency_ms: u64,
+}
+
+/// A check's latest result plus the context the dashboard needs.
+#[derive(Clone)]
+pub struct CheckEntry {
+    pub latest: CheckResult,
+    /// When the check last flipped between healthy and unhealthy (unix seconds);
+    /// the first result's time if it never has.
+    pub changed_at: u64,
+    /// The most recent results, oldest first, including `latest`.
+    pub recent: VecDeque<CheckResult>,
+}
+
+/// Latest poller results keyed by (target, check), shared with the HTTP handlers.
+#[derive(Default)]
+pub struct MonitorState {
+    results: RwLock<HashMap<(String, String), CheckEntry>>,
+    /// Poller rounds completed since startup.
+    rounds: AtomicU64,
+}
+
+impl MonitorState {
+    pub fn round_completed(&self) {
+        self.rounds.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn rounds(&self) -> u64 {
+        self.rounds.load(Ordering::Relaxed)
+    }
+
+    pub fn record(&self, result: CheckResult) {
+        let key = (result.target.clone(), result.check.clone());
+        let mut results = self.results.write().unwrap();
+        match results.get_mut(&key) {
+            Some(entry) => {
+                if entry.latest.healthy != result.healthy {
+                    entry.changed_at = result.checked_at;
+                }
+                if entry.recent.len() == RECENT_RESULTS {
+                    entry.recent.pop_front();
+                }
+                entry.recent.push_back(result.clone());
+                entry.latest = result;
+            }
+            None => {
+                results.insert(
+                    key,
+                    CheckEntry {
+                        changed_at: result.checked_at,
+                        recent: VecDeque::from([result.clone()]),
+                        latest: result,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn get(&self, target: &str, check: &str) -> Option<CheckResult> {
+        self.entry(target, check).map(|e| e.latest)
+    }
+
+    pub fn entry(&self, target: &str, check: &str) -> Option<CheckEntry> {
+        self.results
+            .read()
+            .unwrap()
+            .get(&(target.to_string(), check.to_string()))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result_at(healthy: bool, checked_at: u64) -> CheckResult {
+        CheckResult {
+            checked_at,
+            ..result(healthy)
+        }
+    }
+
+    fn result(healthy: bool) -> CheckResult {
+        CheckResult {
+            target: "l1".to_string(),
+            check: "sync".to_string(),
+            healthy,
+            status: "ok".to_string(),
+            detail: serde_json::Value::Null,
+            checked_at: 1,
+            latency_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_record_replaces_previous_result() {
+        let state = MonitorState::default();
+        state.record(result(true));
+        state.record(result(false));
+        assert!(!state.get("l1", "sync").unwrap().healthy);
+        assert!(state.get("l1", "other").is_none());
+    }
+
+    #[test]
+    fn test_entry_tracks_last_change_and_recent_results() {
+        let state = MonitorState::default();
+        state.record(result_at(true, 10));
+        state.record(result_at(true, 20));
+        state.record(result_at(false, 30));
+        state.record(result_at(false, 40));
+        let entry = state.entry("l1", "sync").unwrap();
+        assert_eq!(entry.changed_at, 30);
+        assert_eq!(entry.recent.len(), 4);
+
+        for i in 0..RECENT_RESULTS as u64 {
+            state.record(result_at(false, 50 + i));
+        }
+        let entry = state.entry("l1", "sync").unwrap();
+        assert_eq!(entry.recent.len(), RECENT_RESULTS);
+        assert_eq!(entry.recent.front().unwrap().checked_at, 50);
+        assert_eq!(entry.changed_at, 30);
+    }
+}
@@ -0,0 +1,289 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{Protocol, WithExportConfig};
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use std::convert::Infallible;
+use std::env;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Instant;
+use tracing::Instrument;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+use warp::http::{HeaderValue, Request, Response};
+use warp::hyper::service::Service;
+use warp::hyper::Body;
+
+/// Header carrying the correlation ID, accepted on incoming requests, echoed on
+/// responses and sent on every outgoing RPC call made while handling them.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Set once by `init` when spans are exported over OTLP.
+static TRACER_PROVIDER: OnceLock<TracerProvider> = OnceLock::new();
+
+/// Install the global subscriber. `RUST_LOG` (full filter syntax, e.g.
+/// `monitor=debug,warp=info`) or `LOG_LEVEL` sets the level, default `info`.
+/// Output is JSON unless `LOG_FORMAT=text`. Spans are also exported over OTLP when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` (or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) is set.
+pub fn init() {
+    let filter = env::var("RUST_LOG")
+        .or_else(|_| env::var("LOG_LEVEL"))
+        .unwrap_or_else(|_| "info".to_string());
+    let filter = EnvFilter::try_new(&filter).unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let fmt = if env::var("LOG_FORMAT").map(|f| f == "text").unwrap_or(false) {
+        tracing_subscriber::fmt::layer().boxed()
+    } else {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed()
+    };
+
+    let endpoint = env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT")
+        .or_else(|_| env::var("OTEL_EXPORTER_OTLP_ENDPOINT"))
+        .ok()
+        .filter(|e| !e.is_empty());
+    let mut otel_error = None;
+    let otel = endpoint.and_then(|endpoint| {
+        let protocol = env::var("OTEL_EXPORTER_OTLP_PROTOCOL").unwrap_or_default();
+        match tracer_provider(&protocol, &endpoint) {
+            Ok(provider) => {
+                let tracer = provider.tracer("monitor");
+                let _ = TRACER_PROVIDER.set(provider);
+                Some(tracing_opentelemetry::layer().with_tracer(tracer))
+            }
+            Err(e) => {
+                otel_error = Some(e.to_string());
+                None
+            }
+        }
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt)
+        .with(otel)
+        .init();
+
+    if let Some(e) = otel_error {
+        tracing::error!(error = %e, "OTLP trace export disabled");
+    }
+}
+
+/// Build a batching OTLP span exporter. `protocol` is `grpc` (the default) or
+/// `http/protobuf`; for HTTP, `/v1/traces` is appended to `endpoint`.
+fn tracer_provider(
+    protocol: &str,
+    endpoint: &str,
+) -> Result<TracerProvider, Box<dyn std::error::Error>> {
+    let exporter = match protocol {
+        "" | "grpc" => opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()?,
+        "http/protobuf" => opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpBinary)
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()?,
+        other => return Err(format!("unsupported OTEL_EXPORTER_OTLP_PROTOCOL '{}'", other).into()),
+    };
+    let service_name = env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "monitor".to_string());
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", service_name)]))
+        .build())
+}
+
+/// Whether spans are exported, so callers can skip work only traces need.
+pub fn otel_enabled() -> bool {
+    TRACER_PROVIDER.get().is_some()
+}
+
+/// Flush spans still queued for export; call before the process exits.
+pub fn shutdown() {
+    if let Some(provider) = TRACER_PROVIDER.get() {
+        let _ = provider.shutdown();
+    }
+}
+
+/// Scheme, host and port of an RPC URL, for span and log fields. Paths, query
+/// strings and credentials are dropped since providers put API keys there.
+pub fn rpc_target(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(u) => match (u.host_str(), u.port()) {
+            (Some(host), Some(port)) => format!("{}://{}:{}", u.scheme(), host, port),
+            (Some(host), None) => format!("{}://{}", u.scheme(), host),
+            (None, _) => u.scheme().to_string(),
+        },
+        Err(_) => "invalid".to_string(),
+    }
+}
+
+pub fn new_request_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// The correlation ID of the request or poller check being handled, if any.
+pub fn request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Run `fut` with `id` as the current correlation ID.
+pub async fn with_request_id<F: Future>(id: String, fut: F) -> F::Output {
+    REQUEST_ID.scope(id, fut).await
+}
+
+/// Reuse a caller-supplied ID when it is short and printable, so a trace can start
+/// at the load balancer; otherwise mint one.
+fn incoming_request_id(req: &Request<Body>) -> String {
+    req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| {
+            !v.is_empty()
+                && v.len() <= 64
+                && v.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(|v| v.to_string())
+        .unwrap_or_else(new_request_id)
+}
+
+/// Serve one HTTP request inside a span carrying its correlation ID, and log the
+/// outcome. Only the path is logged: query strings may carry RPC URLs with API keys.
+pub async fn traced<S>(mut service: S, req: Request<Body>) -> Result<Response<Body>, Infallible>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>,
+{
+    let id = incoming_request_id(&req);
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        http.method = %req.method(),
+        http.path = %req.uri().path(),
+        http.status_code = tracing::field::Empty,
+        otel.kind = "server",
+    );
+    let started = Instant::now();
+
+    let mut response = with_request_id(id.clone(), service.call(req))
+        .instrument(span.clone())
+        .await?;
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    span.record("http.status_code", response.status().as_u16());
+    span.in_scope(|| {
+        tracing::info!(
+            http.status = response.status().as_u16(),
+            latency_ms = started.elapsed().as_millis() as u64,
+            "request completed"
+        )
+    });
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Filter;
+
+    fn service() -> impl Service<Request<Body>, Response = Response<Body>, Error = Infallible> {
+        warp::service(warp::path("id").map(|| request_id().unwrap_or_default()))
+    }
+
+    async fn call(header: Option<&str>) -> (String, String) {
+        let mut req = Request::builder().uri("/id");
+        if let Some(h) = header {
+            req = req.header(REQUEST_ID_HEADER, h);
+        }
+        let response = traced(service(), req.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let echoed = response.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (echoed, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_traced_reuses_valid_incoming_id() {
+        let (echoed, seen_by_handler) = call(Some("lb-1234")).await;
+        assert_eq!(echoed, "lb-1234");
+        assert_eq!(seen_by_handler, "lb-1234");
+    }
+
+    #[tokio::test]
+    async fn test_traced_mints_id_for_missing_or_invalid_header() {
+        let (echoed, seen_by_handler) = call(None).await;
+        assert_eq!(echoed.len(), 16);
+        assert_eq!(echoed, seen_by_handler);
+
+        let (echoed, _) = call(Some("bad id\twith spaces")).await;
+        assert_ne!(echoed, "bad id\twith spaces");
+    }
+
+    #[test]
+    fn test_rpc_target_drops_path_and_credentials() {
+        assert_eq!(
+            rpc_target("https://user:pw@eth.example.com/v3/secret-key?x=1"),
+            "https://eth.example.com"
+        );
+        assert_eq!(rpc_target("http://127.0.0.1:8545"), "http://127.0.0.1:8545");
+        assert_eq!(rpc_target("not a url"), "invalid");
+    }
+
+    #[test]
+    fn test_tracer_provider_rejects_unknown_protocol() {
+        assert!(tracer_provider("http/json", "http://localhost:4318").is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spans_are_exported_to_collector() {
+        let mut collector = mockito::Server::new_async().await;
+        let mock = collector
+            .mock("POST", "/v1/traces")
+            .match_header("content-type", "application/x-protobuf")
+            .match_body(mockito::Matcher::Regex("eth_blockNumber".to_string()))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let provider = tracer_provider("http/protobuf", &collector.url()).unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("rpc", rpc.method = "eth_blockNumber", outcome = "ok")
+                .in_scope(|| {});
+        });
+        // Flushing blocks on the export, which runs on the runtime's other workers.
+        tokio::task::spawn_blocking(move || provider.force_flush())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_request_id_outside_scope_is_none() {
+        assert!(request_id().is_none());
+        let inside = with_request_id("abc".to_string(), async { request_id() }).await;
+        assert_eq!(inside.as_deref(), Some("abc"));
+    }
+}
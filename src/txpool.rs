@@ -0,0 +1,332 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{parse_hex_u128, rpc_call};
+
+#[derive(Serialize)]
+struct TxpoolResponse {
+    pending: u64,
+    queued: u64,
+    // Populated from `txpool_inspect` with `senders=true`; `None` otherwise or when the
+    // node does not expose it.
+    pending_senders: Option<usize>,
+    queued_senders: Option<usize>,
+    alerts: Vec<String>,
+    status: String,
+}
+
+/// Count bounds for `/txpool`; a `None` bound is not checked.
+#[derive(Default)]
+pub struct TxpoolThresholds {
+    pub min_pending: Option<u64>,
+    pub max_pending: Option<u64>,
+    pub min_queued: Option<u64>,
+    pub max_queued: Option<u64>,
+}
+
+fn count_field(result: &Value, field: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    // Geth-style nodes return hex quantities; some return plain numbers.
+    match result.get(field) {
+        Some(Value::String(hex)) => u64::try_from(parse_hex_u128(hex)?)
+            .map_err(|_| format!("txpool_status {} {} does not fit in u64", field, hex).into()),
+        Some(Value::Number(n)) => n
+            .as_u64()
+            .ok_or_else(|| format!("txpool_status {} is not a count", field).into()),
+        _ => Err(format!("txpool_status response has no {}", field).into()),
+    }
+}
+
+async fn get_txpool_status(
+    rpc_url: Option<String>,
+) -> Result<(u64, u64), Box<dyn std::error::Error>> {
+    let result = rpc_call(rpc_url, "txpool_status", vec![]).await?;
+    Ok((
+        count_field(&result, "pending")?,
+        count_field(&result, "queued")?,
+    ))
+}
+
+/// Distinct sender counts from `txpool_inspect`, which maps sender -> nonce -> summary.
+async fn get_txpool_senders(
+    rpc_url: Option<String>,
+) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+    let result = rpc_call(rpc_url, "txpool_inspect", vec![]).await?;
+    let senders = |field: &str| {
+        result
+            .get(field)
+            .and_then(|v| v.as_object())
+            .map(|o| o.len())
+            .unwrap_or(0)
+    };
+    Ok((senders("pending"), senders("queued")))
+}
+
+fn txpool_alerts(pending: u64, queued: u64, thresholds: &TxpoolThresholds) -> Vec<String> {
+    let mut alerts = Vec::new();
+    if let Some(min) = thresholds.min_pending {
+        if pending < min {
+            alerts.push(format!("pending {} below minimum {}", pending, min));
+        }
+    }
+    if let Some(max) = thresholds.max_pending {
+        if pending > max {
+            alerts.push(format!("pending {} above maximum {}", pending, max));
+        }
+    }
+    if let Some(min) = thresholds.min_queued {
+        if queued < min {
+            alerts.push(format!("queued {} below minimum {}", queued, min));
+        }
+    }
+    if let Some(max) = thresholds.max_queued {
+        if queued > max {
+            alerts.push(format!("queued {} above maximum {}", queued, max));
+        }
+    }
+    alerts
+}
+
+/// HTTP handler for `/txpool`. Reports pending and queued transaction counts, and with
+/// `senders` the sender counts when `txpool_inspect` is available; returns 503 when a
+/// count is outside the configured bounds and 500 when `txpool_status` fails.
+pub async fn check_txpool(
+    rpc_url: Option<String>,
+    thresholds: TxpoolThresholds,
+    senders: bool,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (pending, queued) = match get_txpool_status(rpc_url.clone()).await {
+        Ok(counts) => counts,
+        Err(e) => {
+            let error_response = TxpoolResponse {
+                pending: 0,
+                queued: 0,
+                pending_senders: None,
+                queued_senders: None,
+                alerts: vec![],
+                status: format!("error: {}", e),
+            };
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&error_response),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+
+    // `txpool_inspect` returns a summary of every pooled transaction, megabytes on a
+    // busy node, so it is only called on request. Many providers disable it, so a
+    // failure only drops the sender counts instead of failing the check.
+    let (pending_senders, queued_senders) = if senders {
+        match get_txpool_senders(rpc_url).await {
+            Ok((p, q)) => (Some(p), Some(q)),
+            Err(_) => (None, None),
+        }
+    } else {
+        (None, None)
+    };
+
+    let alerts = txpool_alerts(pending, queued, &thresholds);
+    let healthy = alerts.is_empty();
+
+    let response = TxpoolResponse {
+        pending,
+        queued,
+        pending_senders,
+        queued_senders,
+        alerts,
+        status: if healthy {
+            "txpool_ok".to_string()
+        } else {
+            "txpool_out_of_bounds".to_string()
+        },
+    };
+
+    if healthy {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::OK,
+        ))
+    } else {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&response),
+            warp::http::StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::echo_rpc_id;
+    use mockito::{Matcher, Server};
+    use warp::Reply;
+
+    async fn mock_method(server: &mut mockito::ServerGuard, method: &str, body: &str) {
+        server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(format!(
+                r#"{{"method":"{}"}}"#,
+                method
+            )))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(echo_rpc_id(body))
+            .create_async()
+            .await;
+    }
+
+    const STATUS_BODY: &str =
+        r#"{"jsonrpc":"2.0","id":1,"result":{"pending":"0x20","queued":"0x3"}}"#;
+    const INSPECT_BODY: &str = r#"{"jsonrpc":"2.0","id":1,"result":{
+        "pending":{"0x0000000000000000000000000000000000000001":{"0":"summary"},
+                   "0x0000000000000000000000000000000000000002":{"5":"summary"}},
+        "queued":{"0x0000000000000000000000000000000000000003":{"9":"summary"}}}}"#;
+    const NOT_FOUND_BODY: &str =
+        r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"method not found"}}"#;
+
+    async fn txpool_json(
+        server: &mockito::ServerGuard,
+        thresholds: TxpoolThresholds,
+    ) -> (warp::http::StatusCode, serde_json::Value) {
+        let response = check_txpool(Some(server.url()), thresholds, true)
+            .await
+            .unwrap()
+            .into_response();
+        let status = response.status();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_check_txpool_within_bounds_returns_ok() {
+        let mut server = Server::new_async().await;
+        mock_method(&mut server, "txpool_status", STATUS_BODY).await;
+        mock_method(&mut server, "txpool_inspect", INSPECT_BODY).await;
+
+        let thresholds = TxpoolThresholds {
+            min_pending: Some(1),
+            max_pending: Some(100),
+            min_queued: Some(1),
+            max_queued: Some(10),
+        };
+        let (status, json) = txpool_json(&server, thresholds).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(json["pending"], 32);
+        assert_eq!(json["queued"], 3);
+        assert_eq!(json["pending_senders"], 2);
+        assert_eq!(json["queued_senders"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_check_txpool_empty_pool_returns_unavailable() {
+        let mut server = Server::new_async().await;
+        mock_method(
+            &mut server,
+            "txpool_status",
+            r#"{"jsonrpc":"2.0","id":1,"result":{"pending":"0x0","queued":"0x0"}}"#,
+        )
+        .await;
+        mock_method(&mut server, "txpool_inspect", INSPECT_BODY).await;
+
+        let thresholds = TxpoolThresholds {
+            min_pending: Some(1),
+            ..Default::default()
+        };
+        let (status, _) = txpool_json(&server, thresholds).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_check_txpool_saturated_returns_unavailable() {
+        let mut server = Server::new_async().await;
+        mock_method(&mut server, "txpool_status", STATUS_BODY).await;
+        mock_method(&mut server, "txpool_inspect", INSPECT_BODY).await;
+
+        let thresholds = TxpoolThresholds {
+            max_pending: Some(10),
+            ..Default::default()
+        };
+        let (status, json) = txpool_json(&server, thresholds).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["alerts"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_check_txpool_too_few_queued_returns_unavailable() {
+        let mut server = Server::new_async().await;
+        mock_method(&mut server, "txpool_status", STATUS_BODY).await;
+        mock_method(&mut server, "txpool_inspect", INSPECT_BODY).await;
+
+        let thresholds = TxpoolThresholds {
+            min_queued: Some(5),
+            ..Default::default()
+        };
+        let (status, json) = txpool_json(&server, thresholds).await;
+        assert_eq!(status, warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["alerts"][0], "queued 3 below minimum 5");
+    }
+
+    #[tokio::test]
+    async fn test_check_txpool_without_inspect_still_reports_counts() {
+        let mut server = Server::new_async().await;
+        mock_method(&mut server, "txpool_status", STATUS_BODY).await;
+        mock_method(&mut server, "txpool_inspect", NOT_FOUND_BODY).await;
+
+        let (status, json) = txpool_json(&server, TxpoolThresholds::default()).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(json["pending"], 32);
+        assert!(json["pending_senders"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_check_txpool_inspect_is_opt_in() {
+        let mut server = Server::new_async().await;
+        mock_method(&mut server, "txpool_status", STATUS_BODY).await;
+        let inspect = server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"method":"txpool_inspect"}"#.to_string(),
+            ))
+            .expect(0)
+            .create_async()
+            .await;
+
+        let response = check_txpool(Some(server.url()), TxpoolThresholds::default(), false)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["pending"], 32);
+        assert!(json["pending_senders"].is_null());
+        inspect.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_check_txpool_status_unsupported_returns_error() {
+        let mut server = Server::new_async().await;
+        mock_method(&mut server, "txpool_status", NOT_FOUND_BODY).await;
+
+        let (status, _) = txpool_json(&server, TxpoolThresholds::default()).await;
+        assert_eq!(status, warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_count_field_accepts_numbers() {
+        let result: Value = serde_json::from_str(r#"{"pending":7}"#).unwrap();
+        assert_eq!(count_field(&result, "pending").unwrap(), 7);
+        assert!(count_field(&result, "queued").is_err());
+    }
+
+    #[test]
+    fn test_count_field_rejects_counts_above_u64() {
+        let result: Value = serde_json::from_str(r#"{"pending":"0x10000000000000000"}"#).unwrap();
+        assert!(count_field(&result, "pending").is_err());
+        let result: Value = serde_json::from_str(r#"{"pending":"0xffffffffffffffff"}"#).unwrap();
+        assert_eq!(count_field(&result, "pending").unwrap(), u64::MAX);
+    }
+}